@@ -0,0 +1,147 @@
+use clap::Parser;
+
+use pgo_co::{
+    co::{export, CoProblem, FunctionOrdering},
+    fatal_error,
+};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Export CO problem instances to ILP solvers and QAPLIB, and import their solutions
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the CO problem instance
+    #[clap(short = 'p', long = "instance")]
+    inst_path: String,
+
+    /// The instance is a function ordering problem (see the `--functions` option of the
+    /// generator). Its only problem is named `functions`
+    #[clap(long)]
+    functions: bool,
+
+    /// Format to export to or import from: lp (CPLEX LP), mps (free MPS) or qaplib
+    #[clap(short, long, default_value = "lp")]
+    format: Format,
+
+    /// Directory to write a file for each problem of the instance to, named after the problem
+    /// (`main.lp`, `main.mps` or `main.dat`)
+    #[clap(short, long, default_value = ".")]
+    out_dir: String,
+
+    /// Instead of exporting, read the solution file of each problem from this directory
+    /// (`main.sol`, or `main.sln` for qaplib) and write the permutations to `--solutions`.
+    /// Problems without a solution file are skipped
+    #[clap(long)]
+    import: Option<String>,
+
+    /// Path to write the imported permutations to, as expected by the `--apply` option of the
+    /// optimizer
+    #[clap(long, default_value = "solutions.json")]
+    solutions: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Lp,
+    Mps,
+    Qaplib,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lp" => Ok(Format::Lp),
+            "mps" => Ok(Format::Mps),
+            "qaplib" => Ok(Format::Qaplib),
+            _ => Err(format!(
+                "Invalid format {s}. Valid options are: lp, mps and qaplib"
+            )),
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let in_str = match fs::read_to_string(&args.inst_path) {
+        Ok(in_str) => in_str,
+        Err(e) => {
+            fatal_error(format!("Cannot open instance file `{}`: {e}", args.inst_path).as_str())
+        }
+    };
+
+    let problem_set: HashMap<String, CoProblem> = if args.functions {
+        match serde_json::from_str::<FunctionOrdering>(&in_str) {
+            Ok(ordering) => HashMap::from([("functions".to_string(), ordering.problem)]),
+            Err(e) => fatal_error(format!("Failed to parse instance: {e}").as_str()),
+        }
+    } else {
+        match serde_json::from_str(&in_str) {
+            Ok(de) => de,
+            Err(e) => fatal_error(format!("Failed to parse instance: {e}").as_str()),
+        }
+    };
+
+    match &args.import {
+        Some(dir) => import(&args, dir, &problem_set),
+        None => export(&args, &problem_set),
+    }
+}
+
+fn export(args: &Args, problem_set: &HashMap<String, CoProblem>) {
+    for (name, problem) in problem_set.iter() {
+        let (text, ext) = match args.format {
+            Format::Lp => (export::lp(problem, name), "lp"),
+            Format::Mps => (export::mps(problem, name), "mps"),
+            Format::Qaplib => match export::qaplib(problem) {
+                Some(text) => (text, "dat"),
+                None => {
+                    eprintln!(
+                        "{name}: skipped, QAPLIB needs items of equal size and no constraints"
+                    );
+                    continue;
+                }
+            },
+        };
+
+        let path = Path::new(&args.out_dir).join(format!("{name}.{ext}"));
+        if let Err(e) = fs::write(&path, text) {
+            fatal_error(format!("Cannot write `{}`: {e}", path.display()).as_str());
+        }
+    }
+}
+
+fn import(args: &Args, dir: &str, problem_set: &HashMap<String, CoProblem>) {
+    let ext = match args.format {
+        Format::Lp | Format::Mps => "sol",
+        Format::Qaplib => "sln",
+    };
+
+    let mut solutions = HashMap::new();
+    for (name, problem) in problem_set.iter() {
+        let path = Path::new(dir).join(format!("{name}.{ext}"));
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+
+        let solution = match args.format {
+            Format::Lp | Format::Mps => export::read_lp_solution(problem, &text),
+            Format::Qaplib => export::read_qaplib_solution(problem, &text),
+        };
+        match solution {
+            Ok(solution) => solutions.insert(name.clone(), solution),
+            Err(e) => fatal_error(format!("Invalid solution `{}`: {e}", path.display()).as_str()),
+        };
+    }
+
+    if let Err(e) = fs::write(&args.solutions, serde_json::to_string(&solutions).unwrap()) {
+        fatal_error(format!("Cannot write solutions to `{}`: {e}", args.solutions).as_str());
+    }
+}
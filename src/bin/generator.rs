@@ -1,7 +1,8 @@
 use clap::Parser;
 
 use pgo_co::{
-    co::CoProblem,
+    co::{CoProblem, FunctionOrdering},
+    fatal_error,
     profdata::{Function, Module},
 };
 
@@ -35,12 +36,49 @@ struct Args {
     /// order to be considered. Disabled by default
     #[clap(long)]
     max_bb_num: Option<usize>,
+
+    /// Use the machine code size (in bytes) of the basic blocks as their size, instead of
+    /// their number of instructions. The module is compiled to measure them for its own target
+    /// triple with a generic CPU, or for the host CPU if the module doesn't have a triple
+    #[clap(long)]
+    byte_sizes: bool,
+
+    /// Target triple to compile for when measuring the size of the basic blocks in bytes.
+    /// Implies `--byte-sizes`
+    #[clap(long)]
+    target_triple: Option<String>,
+
+    /// Print, for each function, how many branch weights were inferred and how much the
+    /// profile violated flow conservation
+    #[clap(short, long)]
+    verbose: bool,
+
+    /// Generate a single function ordering instance from the call graph of the module, instead
+    /// of a block reordering instance for each function
+    #[clap(long)]
+    functions: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    let module = Module::from_bc_path(args.input_bc_path).unwrap();
+    let mut module = Module::from_bc_path(args.input_bc_path).unwrap();
+
+    if args.byte_sizes || args.target_triple.is_some() {
+        if let Err(e) = module.measure_block_bytes(args.target_triple.as_deref()) {
+            fatal_error(format!("Cannot measure the size of the basic blocks: {e}").as_str());
+        }
+    }
+
+    if args.functions {
+        let ordering = match FunctionOrdering::from_module(&module) {
+            Some(o) => o,
+            None => fatal_error("The module has no profiled calls between its functions"),
+        };
+        write_instance(&args.out_path, serde_json::to_string(&ordering).unwrap());
+        return;
+    }
+
     let mut map = HashMap::new();
 
     let mut functions = module
@@ -55,8 +93,16 @@ fn main() {
     }
 
     for func in functions {
+        let profile = func.infer_branch_weights();
+        if args.verbose {
+            eprintln!(
+                "{}: {} inferred branch weights, {} flow conservation violation",
+                func.name, profile.num_inferred, profile.violation
+            );
+        }
+
         // do nothing if the function has no metadata
-        if let Some(problem) = CoProblem::block_reordering_from(&func) {
+        if let Some(problem) = CoProblem::block_reordering_with(func, &profile.weights) {
             // check minimum number of BBs
             match args.min_bb_num {
                 Some(min) if min > problem.n => continue,
@@ -77,8 +123,11 @@ fn main() {
     }
 
     let inst_str = serde_json::to_string(&map).unwrap();
+    write_instance(&args.out_path, inst_str);
+}
 
-    match args.out_path {
+fn write_instance(out_path: &Option<String>, inst_str: String) {
+    match out_path {
         Some(p) => fs::write(p, inst_str).unwrap(),
         None => println!("{inst_str}"),
     }
@@ -48,7 +48,7 @@ fn main() {
             }
         };
         println!("  + C:");
-        problem.c.iter().for_each(|v| println!("     {:?}", v));
+        (0..problem.n).for_each(|i| println!("     {i}: {:?}", problem.c.successors(i)));
 
         println!("  + s:\n    {:?}", problem.s);
         println!();
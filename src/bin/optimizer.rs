@@ -2,14 +2,41 @@
 use clap::Parser;
 
 use pgo_co::{
-    co::{self, CoProblem},
+    co::{
+        self,
+        aco::{Aco, PheromoneUpdate},
+        cache::{CacheConfig, CacheModel, CacheSim, TraceKind},
+        constructive::Constructive,
+        crossover::Crossover,
+        eda::{Eda, Model},
+        ga::Ga,
+        grasp::Grasp,
+        multi_objective::{
+            Criterion, HotSpread, InsertedJumps, Maximize, ParetoFront, Selection, TakenBranches,
+        },
+        neighborhood::Neighborhood,
+        nsga2::Nsga2,
+        parallel::{Islands, Migration, MultiStart, Topology},
+        sa::SimulatedAnnealing,
+        solver::{self, Budget, Progress, Run, Solver, TimePool},
+        tabu::TabuSearch,
+        vns::Vns,
+        CoProblem, ExtTsp, FunctionOrdering, Interaction, Objective,
+    },
     fatal_error, ir_modifier,
     profdata::Module,
 };
 
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs;
+#[cfg(feature = "log")]
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Optimize profiled LLVM-IR with metaheuristics
 #[derive(Parser, Debug)]
@@ -19,20 +46,306 @@ struct Args {
     #[clap(short = 'p', long = "instance")]
     inst_path: String,
 
-    /// Path to the LLVM bitcode to optimize
+    /// Path to the LLVM bitcode to optimize. If not provided (e.g. for the instances of the
+    /// synthesizer), the problems are only solved and reported
     #[clap(short = 'i', long = "input-bc")]
-    input_bc_path: String,
+    input_bc_path: Option<String>,
 
     /// Path to write the optimized program to
     #[clap(short, long = "out", default_value = "out.ll")]
     out_path: String,
 
+    /// The instance is a function ordering problem (see the `--functions` option of the
+    /// generator). The optimized order is written as a linker symbol ordering file instead of
+    /// modifying the program
+    #[clap(long)]
+    functions: bool,
+
+    /// Path to write the symbol ordering file to (one function name per line, as expected by
+    /// lld's `--symbol-ordering-file`). If not provided, it is dumped to stdout
+    #[clap(long)]
+    symbol_order: Option<String>,
+
     #[clap(short, long, parse(from_occurrences))]
     verbosity: usize,
 
+    /// Before optimizing each function, compute an upper bound of its fitness under every
+    /// objective with checked arithmetic. The functions whose fitness doesn't fit in 64 bits are
+    /// reported, and the ones whose fitness doesn't fit in 128 bits are left unmodified
+    #[clap(long)]
+    checked: bool,
+
+    /// Algorithm to run: constructive, LS, SA, EDA, TS (tabu search), VNS (variable
+    /// neighborhood search), GA (genetic algorithm), ACO (ant colony optimization), GRASP or
+    /// NSGA2. NSGA2 optimizes the selected objective together with the taken branches, the
+    /// unconditional jumps and the spread of the hot code
     #[clap(short, long, default_value = "LS")]
     algorithm: Algorithm,
 
+    /// Exponent of the weights of the blocks when the constructive heuristic (of the
+    /// constructive solver and GRASP) picks the next one
+    #[clap(long, default_value_t = Constructive::default().tau1)]
+    tau1: u32,
+
+    /// Exponent of the weights of the successors of the block picked by the constructive
+    /// heuristic
+    #[clap(long, default_value_t = Constructive::default().tau2)]
+    tau2: u32,
+
+    /// Greediness of the constructions of GRASP, from 0 (only the heaviest candidates) to 1 (all
+    /// the candidates)
+    #[clap(long, default_value_t = Grasp::default().alpha)]
+    grasp_alpha: f64,
+
+    /// Mean number of iterations that the blocks moved by the tabu search can't be moved again
+    #[clap(long, default_value_t = TabuSearch::default().tenure)]
+    tabu_tenure: usize,
+
+    /// Probabilistic model of the EDA: umd (marginals of the items at each position),
+    /// mallows-kendall, mallows-cayley (Mallows models with the Kendall or Cayley distance) or
+    /// plackett-luce
+    #[clap(long, default_value = "umd")]
+    eda_model: Model,
+
+    /// Neighborhood of the simulated annealing: swap, insert, segment:L (moves of segments of up
+    /// to L blocks) or reversal
+    #[clap(long, default_value = "swap")]
+    sa_neighborhood: Neighborhood,
+
+    /// Comma-separated neighborhoods of the VNS, in the order they are explored (see
+    /// `--sa-neighborhood`)
+    #[clap(
+        long,
+        use_value_delimiter = true,
+        default_value = "swap,insert,segment:8,reversal"
+    )]
+    vns_neighborhoods: Vec<Neighborhood>,
+
+    /// Number of random moves of each perturbation of the VNS
+    #[clap(long, default_value_t = Vns::default().perturbation)]
+    vns_perturbation: usize,
+
+    /// Perturbations without improvement after which the VNS restarts from a random solution (0
+    /// to never restart)
+    #[clap(long, default_value_t = Vns::default().restart_after)]
+    vns_restart_after: usize,
+
+    /// Population size of the GA
+    #[clap(long, default_value_t = Ga::default().pop_size)]
+    ga_pop_size: usize,
+
+    /// Crossover of the GA: ox (order), pmx (partially mapped) or cx (cycle)
+    #[clap(long, default_value = "ox")]
+    ga_crossover: Crossover,
+
+    /// Probability of crossing the parents in the GA, instead of copying one of them
+    #[clap(long, default_value_t = Ga::default().crossover_prob)]
+    ga_crossover_prob: f64,
+
+    /// Mutation of the GA, a random move of a neighborhood: swap, insert, reversal (inversion)
+    /// or segment:L
+    #[clap(long, default_value = "insert")]
+    ga_mutation: Neighborhood,
+
+    /// Probability of mutating each child of the GA
+    #[clap(long, default_value_t = Ga::default().mutation_prob)]
+    ga_mutation_prob: f64,
+
+    /// Number of solutions that compete in each tournament selection of the GA
+    #[clap(long, default_value_t = Ga::default().tournament_size)]
+    ga_tournament_size: usize,
+
+    /// Number of the best solutions of the GA that survive to the next generation
+    #[clap(long, default_value_t = Ga::default().elitism)]
+    ga_elitism: usize,
+
+    /// Make the GA steady-state, replacing the worst solution with each new child that is at
+    /// least as good, instead of generational
+    #[clap(long)]
+    ga_steady_state: bool,
+
+    /// Number of ants of each iteration of the ACO
+    #[clap(long, default_value_t = Aco::default().num_ants)]
+    aco_ants: usize,
+
+    /// Exponent of the pheromone in the choices of the ants
+    #[clap(long, default_value_t = Aco::default().alpha)]
+    aco_alpha: f64,
+
+    /// Exponent of the heuristic desirability (the interaction) in the choices of the ants
+    #[clap(long, default_value_t = Aco::default().beta)]
+    aco_beta: f64,
+
+    /// Fraction of the pheromone that evaporates at each iteration of the ACO
+    #[clap(long, default_value_t = Aco::default().rho)]
+    aco_rho: f64,
+
+    /// Pheromone update of the ACO: max-min, or elite:K (rank-based, with the K - 1 best ants
+    /// of each iteration and the best solution found)
+    #[clap(long, default_value = "max-min")]
+    aco_update: PheromoneUpdate,
+
+    /// Number of threads to run the algorithm with (0 for one per CPU core). With more than one,
+    /// the algorithm runs in independent multi-starts with different seeds, or as an island
+    /// model (see `--islands`), which share the budget of each problem
+    #[clap(long, default_value_t = 1)]
+    threads: usize,
+
+    /// Run the GA or the EDA as an island model, with one population per thread that exchange
+    /// their best solutions, instead of in independent multi-starts
+    #[clap(long)]
+    islands: bool,
+
+    /// Number of generations between the migrations of the island model
+    #[clap(long, default_value_t = Migration::default().interval)]
+    migration_interval: usize,
+
+    /// Number of the best solutions each island sends at each migration
+    #[clap(long, default_value_t = Migration::default().migrants)]
+    migrants: usize,
+
+    /// Islands each island receives migrants from: ring (the previous one) or complete (all the
+    /// others)
+    #[clap(long, default_value = "ring")]
+    migration_topology: Topology,
+
+    /// Number of functions optimized at the same time, each on its own worker (0 for as many as
+    /// the CPU cores can run with `--threads` threads each). Each of them runs on `--threads`
+    /// threads. The messages of the workers are printed with the report of their function
+    #[clap(short, long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Seed of the random number generators of the algorithms, each problem using a stream
+    /// derived from the seed and its name. A random seed is used if not provided. The seed is
+    /// written to the output (and the logs). Runs with the same seed and options only give the
+    /// same result when every problem is stopped by `--max-evals` (before `--time-limit`), without
+    /// `--total-time` and, with several `--threads`, without `--target-fitness`: the iterations
+    /// of the rest depend on the wall time, and vary between runs
+    #[clap(long)]
+    seed: Option<u64>,
+
+    /// Maximum time to optimize each problem, in milliseconds
+    #[clap(long, default_value_t = pgo_co::MAX_OPT_MILLIS)]
+    time_limit: u128,
+
+    /// Time to optimize all the functions, e.g. 120s, 2m or 500ms (milliseconds without a
+    /// unit), instead of `--time-limit` for each of them. Half of it is split among the
+    /// functions by their hotness (their entry count, or the total weight of their interactions
+    /// without a program) and their size, with at least a slice each (or an equal part of the
+    /// time, if it is too short for that), and the rest is given in slices of `--time-slice` to
+    /// the functions that are still improving when their share is over. The time split is the
+    /// one of all the `--jobs` workers together, and all of them stop when the total time is
+    /// over
+    #[clap(long, parse(try_from_str = parse_millis))]
+    total_time: Option<u128>,
+
+    /// Milliseconds of each slice of the time of `--total-time` given to the functions that are
+    /// still improving
+    #[clap(long, default_value_t = 200)]
+    time_slice: u128,
+
+    /// Maximum number of evaluations to optimize each problem with
+    #[clap(long)]
+    max_evals: Option<usize>,
+
+    /// Stop optimizing a problem as soon as a solution with this fitness is found
+    #[clap(long)]
+    target_fitness: Option<u128>,
+
+    /// Rule to pick the solution to apply from the Pareto front found by NSGA2: knee, or
+    /// weighted:w1,w2,w3,w4 (weights of the criteria, normalized over the front)
+    #[clap(long, default_value = "knee")]
+    selection: Selection,
+
+    /// Population size of NSGA2
+    #[clap(long, default_value_t = Nsga2::default().pop_size)]
+    nsga2_pop_size: usize,
+
+    /// Probability of mutating each child of NSGA2
+    #[clap(long, default_value_t = Nsga2::default().mutation_prob)]
+    nsga2_mutation_prob: f64,
+
+    /// Path to write the Pareto front found by NSGA2 for each function to (in JSON)
+    #[clap(long)]
+    pareto_front: Option<String>,
+
+    /// Problems with fewer items than this are solved exactly: by dynamic programming with the
+    /// interaction objective, and by branch and bound with the rest. Branch and bound gets half
+    /// of the time of the problem, and if it can't prove the optimum in it, the selected
+    /// algorithm gets the rest and the best of both solutions is kept (0 to disable)
+    #[clap(long, default_value_t = 0)]
+    exact_below: usize,
+
+    /// Run the selected algorithm on the problems below `--exact-below` too, and report how far
+    /// it is from the optimum found by the exact solver
+    #[clap(long)]
+    ground_truth: bool,
+
+    /// Apply the solutions of the given file (a map from function name to permutation, as
+    /// written by the `--import` option of the exporter) instead of optimizing. The functions
+    /// missing from the file are optimized as usual
+    #[clap(long)]
+    apply: Option<String>,
+
+    /// Objective function to maximize: interaction, ext-tsp or cache
+    #[clap(long, default_value = "interaction")]
+    objective: ObjectiveKind,
+
+    /// Don't optimize, only evaluate the identity order of the items of each problem under all
+    /// the objectives (including the i-cache and iTLB misses of the cache objective). That is the
+    /// layout of the program the instance was generated from, which is not the one of the
+    /// input program if it was reordered since
+    #[clap(long)]
+    evaluate: bool,
+
+    /// Maximum distance of the forward jumps rewarded by the Ext-TSP objective
+    #[clap(long, default_value_t = 1024)]
+    forward_distance: u64,
+
+    /// Maximum distance of the backward jumps rewarded by the Ext-TSP objective
+    #[clap(long, default_value_t = 640)]
+    backward_distance: u64,
+
+    /// How the cache objective generates execution traces: walk (random walks over the
+    /// profile) or stationary (samples of the stationary distribution of the profile)
+    #[clap(long, default_value = "walk")]
+    trace: TraceKind,
+
+    /// Length of the execution trace of each problem simulated by the cache objective. The
+    /// traces are drawn from streams derived from `--seed` too
+    #[clap(long, default_value_t = 20000)]
+    trace_len: usize,
+
+    /// Line size of the simulated i-cache, in bytes
+    #[clap(long, default_value_t = 64)]
+    cache_line_size: u64,
+
+    /// Number of sets of the simulated i-cache
+    #[clap(long, default_value_t = 64)]
+    cache_sets: usize,
+
+    /// Associativity of the simulated i-cache
+    #[clap(long, default_value_t = 8)]
+    cache_ways: usize,
+
+    /// Page size of the simulated iTLB, in bytes
+    #[clap(long, default_value_t = 4096)]
+    page_size: u64,
+
+    /// Number of sets of the simulated iTLB
+    #[clap(long, default_value_t = 16)]
+    tlb_sets: usize,
+
+    /// Associativity of the simulated iTLB
+    #[clap(long, default_value_t = 4)]
+    tlb_ways: usize,
+
+    /// Size in bytes of each size unit of the instance, used by the cache objective. Use 1 if
+    /// the instance was generated with `--byte-sizes`, or an estimate of the average instruction
+    /// size otherwise
+    #[clap(long, default_value_t = 1)]
+    size_unit_bytes: u64,
+
     #[cfg(feature = "log")]
     #[clap(short, long, default_value = ".")]
     log_path: String,
@@ -40,125 +353,452 @@ struct Args {
 
 #[derive(Debug)]
 enum Algorithm {
-    Constructive,
-    LocalSearch,
-    SimulatedAnnealing,
-    Eda,
+    /// Name of a single-objective solver of the registry
+    Solver(String),
+    Nsga2,
 }
 
 impl FromStr for Algorithm {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use Algorithm::*;
+        let names = solver::registry()
+            .iter()
+            .map(|s| s.name())
+            .collect::<Vec<&str>>();
         match s {
-            "constructive" => Ok(Constructive),
-            "LS" => Ok(LocalSearch),
-            "SA" => Ok(SimulatedAnnealing),
-            "EDA" => Ok(Eda),
+            "NSGA2" => Ok(Algorithm::Nsga2),
+            _ if names.contains(&s) => Ok(Algorithm::Solver(s.to_string())),
             _ => Err(format!(
-                "Invalid algorithm {s}. Valid options are: constructive, LS, SA and EDA"
+                "Invalid algorithm {s}. Valid options are: {} and NSGA2",
+                names.join(", ")
+            )),
+        }
+    }
+}
+
+/// The values index the array of objectives built for each problem
+#[derive(Debug, Clone, Copy)]
+enum ObjectiveKind {
+    Interaction = 0,
+    ExtTsp = 1,
+    Cache = 2,
+}
+
+impl FromStr for ObjectiveKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interaction" => Ok(ObjectiveKind::Interaction),
+            "ext-tsp" => Ok(ObjectiveKind::ExtTsp),
+            "cache" => Ok(ObjectiveKind::Cache),
+            _ => Err(format!(
+                "Invalid objective {s}. Valid options are: interaction, ext-tsp and cache"
             )),
         }
     }
 }
 
 fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if args.ga_elitism >= args.ga_pop_size || args.ga_tournament_size == 0 {
+        fatal_error("The GA needs ga-elitism < ga-pop-size and ga-tournament-size > 0");
+    }
+    let probs = [
+        args.ga_crossover_prob,
+        args.ga_mutation_prob,
+        args.nsga2_mutation_prob,
+        args.grasp_alpha,
+    ];
+    if probs.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        fatal_error("Probabilities and grasp-alpha must be in [0, 1]");
+    }
+    if args.nsga2_pop_size == 0 {
+        fatal_error("NSGA2 needs nsga2-pop-size > 0");
+    }
+    if args.aco_ants == 0 || args.aco_rho <= 0.0 || args.aco_rho > 1.0 {
+        fatal_error("The ACO needs aco-ants > 0 and aco-rho in (0, 1]");
+    }
+    let cache_sizes = [
+        args.cache_line_size,
+        args.cache_sets as u64,
+        args.cache_ways as u64,
+        args.page_size,
+        args.tlb_sets as u64,
+        args.tlb_ways as u64,
+        args.size_unit_bytes,
+    ];
+    if cache_sizes.contains(&0) {
+        fatal_error("The sizes, sets and ways of the simulated i-cache and iTLB must be > 0");
+    }
+    if args.islands {
+        if !matches!(&args.algorithm, Algorithm::Solver(s) if s == "GA" || s == "EDA") {
+            fatal_error("The island model is only available for the GA and the EDA");
+        }
+        if args.migration_interval == 0 {
+            fatal_error("The island model needs migration-interval > 0");
+        }
+    }
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    if args.threads == 0 {
+        args.threads = cores;
+    }
+    if args.jobs == 0 {
+        args.jobs = (cores / args.threads).max(1);
+    } else if args.jobs.saturating_mul(args.threads) > cores {
+        eprintln!(
+            "\x1b[33;1m[WARNING]\x1b[0m {} jobs on {} threads each need more than the {cores} CPU \
+             cores, which slows down the algorithms and gives them less time",
+            args.jobs, args.threads
+        );
+    }
+    if args.seed.is_some()
+        && (args.max_evals.is_none()
+            || args.total_time.is_some()
+            || (args.threads > 1 && args.target_fitness.is_some()))
+    {
+        eprintln!(
+            "\x1b[33;1m[WARNING]\x1b[0m The result is not reproducible with the seed alone: it \
+             depends on the wall time unless the run is limited by --max-evals, without \
+             --total-time and, with several --threads, without --target-fitness"
+        );
+    }
+    // from here on the seed is always known, so that it can be reported
+    let seed = *args.seed.get_or_insert_with(rand::random);
+    if args.verbosity > 0 {
+        println!("Seed: {seed}\n");
+    }
 
-    // deserialize CO problem instance
-    let problem_set: HashMap<String, CoProblem> = match fs::read_to_string(&args.inst_path) {
-        Ok(in_str) => match serde_json::from_str(&in_str) {
-            Ok(de) => de,
-            Err(e) => fatal_error(format!("Failed to parse instance: {e}").as_str()),
-        },
+    let in_str = match fs::read_to_string(&args.inst_path) {
+        Ok(in_str) => in_str,
         Err(e) => {
             fatal_error(format!("Cannot open instance file `{}`: {e}", args.inst_path).as_str())
         }
     };
 
-    let module = Module::from_bc_path(&args.input_bc_path).unwrap();
+    let module = args
+        .input_bc_path
+        .as_ref()
+        .map(|path| Module::from_bc_path(path).unwrap());
+
+    // solutions to apply instead of optimizing
+    let applied: HashMap<String, Vec<usize>> = match &args.apply {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(s) => match serde_json::from_str(&s) {
+                Ok(de) => de,
+                Err(e) => fatal_error(format!("Failed to parse solutions: {e}").as_str()),
+            },
+            Err(e) => fatal_error(format!("Cannot open solutions file `{path}`: {e}").as_str()),
+        },
+        None => HashMap::new(),
+    };
+
+    let ext_tsp = ExtTsp {
+        forward_distance: args.forward_distance,
+        backward_distance: args.backward_distance,
+        ..ExtTsp::default()
+    };
+    let cache_model = CacheModel {
+        icache: CacheConfig {
+            line_size: args.cache_line_size,
+            num_sets: args.cache_sets,
+            ways: args.cache_ways,
+        },
+        itlb: CacheConfig {
+            line_size: args.page_size,
+            num_sets: args.tlb_sets,
+            ways: args.tlb_ways,
+        },
+        unit_bytes: args.size_unit_bytes,
+        ..CacheModel::default()
+    };
     // let mut rng = rand::thread_rng();
 
     #[cfg(feature = "log")]
-    pgo_co::log::set_log_dir(args.log_path);
+    pgo_co::log::set_log_dir(&args.log_path);
 
     #[cfg(feature = "log")]
-    let opt_file = PathBuf::from(args.input_bc_path)
-        .file_stem()
-        .unwrap()
-        .to_string_lossy()
-        .to_string();
+    let opt_file = match &args.input_bc_path {
+        Some(path) => PathBuf::from(path)
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .to_string(),
+        None => "-".to_string(),
+    };
 
     #[cfg(feature = "log")]
-    let inst_name = PathBuf::from(args.inst_path)
+    let inst_name = PathBuf::from(&args.inst_path)
         .file_stem()
         .unwrap()
         .to_string_lossy()
         .to_string();
 
-    // for function in &module.functions {
-    for (fn_name, problem) in problem_set.iter() {
-        let function =
-            match module.get_function(fn_name) {
-                Some(f) => f,
-                None => fatal_error(
-                    format!(
-                        "Function `{fn_name}` exists in the instance but not in the input program",
-                    )
+    if args.functions {
+        // deserialize the function ordering instance
+        let ordering: FunctionOrdering = match serde_json::from_str(&in_str) {
+            Ok(de) => de,
+            Err(e) => fatal_error(format!("Failed to parse instance: {e}").as_str()),
+        };
+        let problem = &ordering.problem;
+
+        // all the available objectives, the selected one is optimized and the rest reported
+        let trace_seed = solver::stream_seed(seed, "Function ordering trace");
+        let cache = CacheSim::new(problem, cache_model, args.trace, args.trace_len, trace_seed);
+        let objectives: [&dyn Objective; 3] = [&Interaction, &ext_tsp, &cache];
+        let objective = objectives[args.objective as usize];
+
+        let messages = Messages::new(false);
+        if args.checked && !check_fitness("Function ordering", &objectives, problem, &messages) {
+            fatal_error("The fitness of the function ordering problem cannot be computed");
+        }
+
+        if args.evaluate {
+            print_evaluation("Function ordering", &objectives, problem, &cache);
+            return;
+        }
+
+        if let Some(name) = module.as_ref().and_then(|module| {
+            ordering
+                .functions
+                .iter()
+                .find(|name| module.get_function(name).is_none())
+        }) {
+            fatal_error(
+                format!("Function `{name}` exists in the instance but not in the input program")
                     .as_str(),
-                ),
-            };
-        /*
-            let problem = match problem_set.get(&.name) {
-                Some(p) => p,
-                None => fatal_error(
+            );
+        }
+
+        #[cfg(feature = "log")]
+        {
+            pgo_co::log::set_attr("function", "*function ordering*");
+            pgo_co::log::set_attr("num functions", problem.n);
+            pgo_co::log::set_attr("opt file", &opt_file);
+            pgo_co::log::set_attr("instance", &inst_name);
+            pgo_co::log::set_attr("objective", objective.name());
+            pgo_co::log::set_attr("max opt time", args.total_time.unwrap_or(args.time_limit));
+            pgo_co::log::set_attr("seed", seed);
+        }
+
+        let solved = match applied.get("functions") {
+            Some(solution) => imported("Function ordering", problem, objective, solution),
+            None => {
+                // the only problem gets the whole budget
+                let time = TimeShare {
+                    millis: args.total_time.unwrap_or(args.time_limit),
+                    pool: None,
+                    deadline: None,
+                };
+                solve(
+                    &args,
+                    "Function ordering",
+                    problem,
+                    objective,
+                    &time,
+                    &messages,
+                )
+            }
+        };
+
+        if args.verbosity > 0 {
+            print_report(
+                "Function ordering",
+                &args,
+                &objectives,
+                problem,
+                &cache,
+                &solved,
+            );
+        }
+
+        if let Some(front) = solved.front {
+            write_fronts(
+                &args,
+                &HashMap::from([("function ordering".to_string(), front)]),
+            );
+        }
+
+        // lld ignores the lines starting with `#`
+        let order_str = format!(
+            "# seed: {seed}\n{}",
+            ordering.function_order(&solved.solution).join("\n")
+        );
+        match &args.symbol_order {
+            Some(p) => {
+                if let Err(e) = fs::write(p, order_str + "\n") {
+                    fatal_error(format!("Cannot write symbol order to `{p}`: {e}").as_str());
+                }
+            }
+            None => println!("{order_str}"),
+        }
+
+        return;
+    }
+
+    // deserialize CO problem instance
+    let problem_set: HashMap<String, CoProblem> = match serde_json::from_str(&in_str) {
+        Ok(de) => de,
+        Err(e) => fatal_error(format!("Failed to parse instance: {e}").as_str()),
+    };
+
+    // Pareto front of each function, if the algorithm is multi-objective
+    let mut fronts = HashMap::new();
+
+    // the functions of the program are only looked up and modified from the main thread, while
+    // their problems are solved on the workers. The largest problems go first, so that they don't
+    // keep a single worker busy at the end
+    let mut problems = problem_set.iter().collect::<Vec<(&String, &CoProblem)>>();
+    problems.sort_by_key(|(name, problem)| (Reverse(problem.n), *name));
+
+    // for function in &module.functions {
+    let functions = problems
+        .iter()
+        .map(|(fn_name, problem)| {
+            let function = module
+                .as_ref()
+                .map(|module| match module.get_function(fn_name) {
+                    Some(f) => f,
+                    None => fatal_error(
+                        format!(
+                            "Function `{fn_name}` exists in the instance but not in the input \
+                             program"
+                        )
+                        .as_str(),
+                    ),
+                });
+            /*
+                let problem = match problem_set.get(&.name) {
+                    Some(p) => p,
+                    None => fatal_error(
+                        format!(
+                            "Function `{}` exists in the input program but not in the instance",
+                            function.name
+                        )
+                        .as_str(),
+                    ),
+                };
+            */
+
+            // instances written by older versions of the generator don't include the entry block
+            let num_bbs = function.map_or(problem.n, |f| f.num_bbs);
+            if problem.n != num_bbs && problem.n + 1 != num_bbs {
+                fatal_error(
                     format!(
-                        "Function `{}` exists in the input program but not in the instance",
-                        function.name
+                        "Function `{fn_name}` has {num_bbs} blocks, but its instance has {} items",
+                        problem.n
                     )
                     .as_str(),
-                ),
-            };
-        */
+                );
+            }
+            (function, num_bbs)
+        })
+        .collect::<Vec<_>>();
+    // the entry counts of the functions, if all of them have one
+    let entry_counts = functions
+        .iter()
+        .map(|(function, _)| function.and_then(|f| f.get_entry_count()))
+        .collect::<Option<Vec<u64>>>();
+    let times = time_shares(&args, &problems, entry_counts);
+
+    let jobs = problems
+        .iter()
+        .zip(&functions)
+        .zip(times)
+        .map(|(((fn_name, problem), (_, num_bbs)), time)| (*fn_name, *problem, *num_bbs, time))
+        .collect::<Vec<(&String, &CoProblem, usize, TimeShare)>>();
+
+    // the messages of each function, its cache objective, and its solution and the fitness of
+    // its identity unless it is only evaluated
+    let outcomes = parallel_map(&jobs, args.jobs, |job| {
+        let (fn_name, problem) = (job.0, job.1);
+        let messages = Messages::new(args.jobs > 1);
+
+        // all the available objectives, the selected one is optimized and the rest reported
+        // a stream of its own, apart from the one of the algorithm
+        let trace_seed = solver::stream_seed(seed, &format!("{fn_name} trace"));
+        let cache = CacheSim::new(problem, cache_model, args.trace, args.trace_len, trace_seed);
+        let objectives: [&dyn Objective; 3] = [&Interaction, &ext_tsp, &cache];
+        let objective = objectives[args.objective as usize];
+
+        if args.checked && !check_fitness(fn_name, &objectives, problem, &messages) {
+            return (messages, None);
+        }
+
+        if args.evaluate {
+            return (messages, Some((cache, None)));
+        }
 
         let identity = (0..problem.n).collect::<Vec<usize>>();
-        let iden_fitness = problem.eval(&identity);
+        let iden_fitness = objective.eval(problem, &identity);
 
         #[cfg(feature = "log")]
         {
-            pgo_co::log::set_attr("function", &function.name);
-            pgo_co::log::set_attr("num blocks", &function.num_bbs);
+            pgo_co::log::set_attr("function", fn_name);
+            pgo_co::log::set_attr("num blocks", job.2);
             pgo_co::log::set_attr("opt file", &opt_file);
             pgo_co::log::set_attr("instance", &inst_name);
+            pgo_co::log::set_attr("objective", objective.name());
             pgo_co::log::set_attr("identity fitness", iden_fitness);
-            pgo_co::log::set_attr("max opt time", pgo_co::MAX_OPT_MILLIS);
+            pgo_co::log::set_attr("max opt time", job.3.millis);
+            pgo_co::log::set_attr("seed", seed);
         }
 
-        let (opt_sol, opt_fitness) = match args.algorithm {
-            Algorithm::LocalSearch => co::local_search::run(&problem),
-            Algorithm::Constructive => co::constructive::construct_solution(&problem, 3, 2),
-            Algorithm::SimulatedAnnealing => co::sa::run(&problem, 1000000.0, 0.95, 10000.0, 100),
-            Algorithm::Eda => co::eda::run(&problem, 300, 100),
+        let solved = match applied.get(fn_name) {
+            Some(solution) => imported(fn_name, problem, objective, solution),
+            None => solve(&args, fn_name, problem, objective, &job.3, &messages),
         };
+        (messages, Some((cache, Some((solved, iden_fitness)))))
+    });
 
-        if opt_fitness > iden_fitness {
-            ir_modifier::reorder_blocks(function.function_ref, &opt_sol);
-        }
+    for ((fn_name, problem), ((function, num_bbs), (messages, outcome))) in problems
+        .into_iter()
+        .zip(functions.into_iter().zip(outcomes))
+    {
+        messages.print();
 
-        if args.verbosity > 0 {
-            println!("{}", function.name);
+        // the fitness of the function can't be computed
+        let (cache, solved) = match outcome {
+            Some(outcome) => outcome,
+            None => continue,
+        };
+        let objectives: [&dyn Objective; 3] = [&Interaction, &ext_tsp, &cache];
 
-            if opt_fitness <= iden_fitness {
-                println!("*** Function not optimized ***");
+        let (solved, iden_fitness) = match solved {
+            Some(solved) => solved,
+            None => {
+                print_evaluation(fn_name, &objectives, problem, &cache);
+                continue;
             }
+        };
 
-            println!("  * Fitness of identity: {}", iden_fitness);
-            println!("  * {:?} fitness: {}\n", args.algorithm, opt_fitness);
-            if args.verbosity > 1 {
-                println!("  * Optimized solution: {:?}", opt_sol);
+        let without_entry = problem.n + 1 == num_bbs;
+        match function {
+            Some(function) if solved.fitness > iden_fitness => {
+                let order = if without_entry {
+                    std::iter::once(0)
+                        .chain(solved.solution.iter().map(|bb| bb + 1))
+                        .collect()
+                } else {
+                    solved.solution.clone()
+                };
+                ir_modifier::reorder_blocks(function.function_ref, &order);
             }
+            _ => (),
         }
+
+        if args.verbosity > 0 {
+            print_report(fn_name, &args, &objectives, problem, &cache, &solved);
+        }
+
+        if let Some(front) = solved.front {
+            fronts.insert(fn_name.to_string(), front);
+        }
+    }
+
+    if !fronts.is_empty() {
+        write_fronts(&args, &fronts);
     }
 
     /*
@@ -199,7 +839,612 @@ fn main() {
     }
     */
 
-    if let Err(e) = module.to_path(&args.out_path) {
-        fatal_error(format!("Cannot write output to `{}`: {e}", &args.out_path).as_str());
+    if args.evaluate {
+        return;
+    }
+
+    if let Some(module) = module {
+        module.add_named_metadata("pgo_co.seed", &seed.to_string());
+        if let Err(e) = module.to_path(&args.out_path) {
+            fatal_error(format!("Cannot write output to `{}`: {e}", &args.out_path).as_str());
+        }
+    }
+}
+
+/// Maps `f` over the items on `threads` worker threads, which take them in order. The results
+/// keep the order of the items.
+fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(items.iter().map(|_| None).collect::<Vec<Option<R>>>());
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= items.len() {
+                    break;
+                }
+                let result = f(&items[i]);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// Computes the bound of the fitness of `problem` under each of the objectives, reporting the
+/// ones that overflow 64 bits. Returns `false` if some of them overflows 128 bits.
+fn check_fitness(
+    name: &str,
+    objectives: &[&dyn Objective],
+    problem: &CoProblem,
+    messages: &Messages,
+) -> bool {
+    let mut fits = true;
+    for objective in objectives {
+        match objective.max_fitness(problem) {
+            Some(max) if max <= u64::MAX as u128 => (),
+            Some(max) => messages.push(format!(
+                "{name}: the {} fitness can reach {max}, which overflows 64 bits",
+                objective.name()
+            )),
+            None => {
+                messages.push(format!(
+                    "{name}: the {} fitness overflows 128 bits, skipping",
+                    objective.name()
+                ));
+                fits = false;
+            }
+        }
+    }
+    fits
+}
+
+/// Messages of a worker, printed as they come when there's a single worker, or held to be printed
+/// with the report of their function otherwise, so that those of the functions don't mix.
+#[derive(Clone)]
+struct Messages(Option<Arc<Mutex<Vec<String>>>>);
+
+impl Messages {
+    fn new(held: bool) -> Self {
+        Messages(held.then(Arc::default))
+    }
+
+    fn push(&self, message: String) {
+        match &self.0 {
+            Some(held) => held.lock().unwrap().push(message),
+            None => eprintln!("{message}"),
+        }
+    }
+
+    /// Prints the messages held so far.
+    fn print(&self) {
+        if let Some(held) = &self.0 {
+            for message in held.lock().unwrap().drain(..) {
+                eprintln!("{message}");
+            }
+        }
+    }
+}
+
+/// Solution of a problem and how it was found.
+struct Solved {
+    solution: Vec<usize>,
+    fitness: u128,
+    /// Name of the algorithm that found the solution
+    algorithm: String,
+    /// Pareto front the solution was picked from, for the multi-objective algorithms
+    front: Option<ParetoFront>,
+    /// Upper bound of the optimal fitness, if the problem was solved by (or checked with) the
+    /// exact solver
+    bound: Option<u128>,
+    evaluations: usize,
+    millis: u128,
+}
+
+/// Time to optimize a problem with: `--time-limit`, or its share of `--total-time`, the pool of
+/// the time held back and the end of the total time.
+#[derive(Clone)]
+struct TimeShare {
+    millis: u128,
+    pool: Option<Arc<TimePool>>,
+    deadline: Option<Instant>,
+}
+
+/// Time of each of the problems, as described in `--total-time`.
+fn time_shares(
+    args: &Args,
+    problems: &[(&String, &CoProblem)],
+    entry_counts: Option<Vec<u64>>,
+) -> Vec<TimeShare> {
+    let total = match args.total_time {
+        Some(total) => total,
+        None => {
+            let time = TimeShare {
+                millis: args.time_limit,
+                pool: None,
+                deadline: None,
+            };
+            return vec![time; problems.len()];
+        }
+    };
+
+    // the workers run at the same time, so the time split is the one of all of them, but none
+    // of the functions gets more than the total time and all of them stop at its end
+    let wall = total;
+    let deadline =
+        Instant::now().checked_add(Duration::from_millis(wall.min(u64::MAX as u128) as u64));
+    let total = total * args.jobs.clamp(1, problems.len().max(1)) as u128;
+    let pool = Arc::new(TimePool::new(total / 2, args.time_slice));
+
+    let hotness = match entry_counts {
+        Some(counts) => counts.iter().map(|c| *c as f64).collect(),
+        None => problems
+            .iter()
+            .map(|(_, p)| p.c.edges().map(|(_, _, w)| w as f64).sum())
+            .collect::<Vec<f64>>(),
+    };
+    let hot_sum = hotness.iter().sum::<f64>();
+    let size_sum = problems
+        .iter()
+        .map(|(_, p)| p.n as f64)
+        .sum::<f64>()
+        .max(1.0);
+    let min_millis = args
+        .time_slice
+        .min(total / problems.len().max(1) as u128)
+        .min(wall)
+        .max(1);
+
+    problems
+        .iter()
+        .zip(hotness)
+        .map(|((_, problem), hot)| {
+            let size = problem.n as f64 / size_sum;
+            let hot = if hot_sum > 0.0 { hot / hot_sum } else { size };
+            // at least the minimum, so that even the coldest functions start optimizing, taken
+            // from the pool as long as it lasts
+            let millis = (((total - total / 2) as f64 * (hot + size) / 2.0) as u128).min(wall);
+            TimeShare {
+                millis: millis + pool.take(min_millis.saturating_sub(millis)),
+                pool: Some(pool.clone()),
+                deadline,
+            }
+        })
+        .collect()
+}
+
+/// Parses a duration in milliseconds, with an optional unit: ms, s, m or h.
+fn parse_millis(s: &str) -> Result<u128, String> {
+    let (number, scale) = [("ms", 1.0), ("s", 1e3), ("m", 6e4), ("h", 3.6e6)]
+        .iter()
+        .find_map(|(unit, scale)| s.strip_suffix(unit).map(|n| (n, *scale)))
+        .unwrap_or((s, 1.0));
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok((n * scale).round() as u128),
+        _ => Err(format!(
+            "Invalid duration {s}. Valid formats are a number of milliseconds, or a number \
+             followed by ms, s, m or h"
+        )),
+    }
+}
+
+/// Solves the problem exactly if it is small enough, otherwise runs the selected algorithm. If
+/// the exact solver can't prove the optimum in its time, the selected algorithm is run too.
+fn solve(
+    args: &Args,
+    name: &str,
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    time: &TimeShare,
+    messages: &Messages,
+) -> Solved {
+    // the multi-objective algorithms can't be replaced by the exact solver
+    let exact = problem.n < args.exact_below && !matches!(args.algorithm, Algorithm::Nsga2);
+    if !exact {
+        return run_algorithm(args, name, problem, objective, time, messages);
+    }
+
+    // the other half of the time is left for the algorithm
+    let exact_time = TimeShare {
+        millis: time.millis / 2,
+        ..time.clone()
+    };
+    let mut run = new_run(args, name, problem, objective, &exact_time, messages);
+    let (exact, algorithm) = match args.objective {
+        ObjectiveKind::Interaction => co::exact::held_karp(problem).map(|e| (e, "HeldKarp")),
+        _ => None,
+    }
+    .unwrap_or_else(|| {
+        (
+            co::exact::branch_and_bound(problem, objective, &mut run),
+            "BranchAndBound",
+        )
+    });
+
+    // gives the time left back to the pool
+    let (bound, optimal) = (exact.bound, exact.is_optimal());
+    let result = run.finish(exact.solution, exact.fitness);
+    let exact_solved = Solved {
+        solution: result.solution,
+        fitness: result.fitness,
+        algorithm: algorithm.to_string(),
+        front: None,
+        bound: Some(bound),
+        evaluations: result.evaluations,
+        millis: result.millis,
+    };
+    if optimal && !args.ground_truth {
+        return exact_solved;
     }
+
+    // with a pool, the time that the exact solver left is in the pool
+    let left = time.millis - exact_time.millis;
+    let time = match &time.pool {
+        Some(pool) => TimeShare {
+            millis: left + pool.take(exact_time.millis.saturating_sub(result.millis)),
+            ..time.clone()
+        },
+        None => TimeShare {
+            millis: left + exact_time.millis.saturating_sub(result.millis),
+            ..time.clone()
+        },
+    };
+    let solved = run_algorithm(args, name, problem, objective, &time, messages);
+
+    if args.ground_truth {
+        return Solved {
+            bound: Some(bound),
+            ..solved
+        };
+    }
+
+    // the best of both solutions, found with the effort of both
+    let (evaluations, millis) = (
+        exact_solved.evaluations + solved.evaluations,
+        exact_solved.millis + solved.millis,
+    );
+    let best = if solved.fitness > exact_solved.fitness {
+        solved
+    } else {
+        exact_solved
+    };
+    Solved {
+        bound: Some(bound),
+        evaluations,
+        millis,
+        ..best
+    }
+}
+
+/// A run with the budget of the arguments and the given time that, if verbose enough, reports
+/// the improvements of the best fitness of the problem to `messages`.
+fn new_run(
+    args: &Args,
+    name: &str,
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    time: &TimeShare,
+    messages: &Messages,
+) -> Run {
+    let budget = Budget {
+        millis: time.millis,
+        evaluations: args.max_evals,
+        target: args.target_fitness,
+        deadline: time.deadline,
+    };
+    let mut run = Run::new(budget, solver::stream_seed(args.seed.unwrap(), name));
+    if let Some(pool) = &time.pool {
+        // the improvements of the problems compare relative to their largest fitness
+        let max_fitness = objective.max_fitness(problem).unwrap_or(u128::MAX);
+        run = run.with_pool(pool.clone(), max_fitness);
+    }
+
+    if args.verbosity > 2 {
+        let (name, messages) = (name.to_string(), messages.clone());
+        run.with_observer(move |p: &Progress| {
+            messages.push(format!(
+                "{name}: best fitness {} after {} evaluations ({} ms)",
+                p.best_fitness, p.evaluations, p.millis
+            ))
+        })
+    } else {
+        run
+    }
+}
+
+/// The solver of the registry with the given name, configured with the arguments, and run on
+/// `--threads` threads.
+fn build_solver(args: &Args, name: &str) -> Box<dyn Solver> {
+    if args.threads > 1 && args.islands {
+        let migration = Migration {
+            interval: args.migration_interval,
+            migrants: args.migrants,
+            topology: args.migration_topology,
+        };
+        return match name {
+            "GA" => Box::new(Islands {
+                solver: build_ga(args),
+                islands: args.threads,
+                migration,
+            }),
+            "EDA" => Box::new(Islands {
+                solver: build_eda(args),
+                islands: args.threads,
+                migration,
+            }),
+            _ => unreachable!(),
+        };
+    }
+
+    let solver: Box<dyn Solver> = match name {
+        "SA" => Box::new(SimulatedAnnealing {
+            neighborhood: args.sa_neighborhood,
+            ..Default::default()
+        }),
+        "TS" => Box::new(TabuSearch {
+            tenure: args.tabu_tenure,
+        }),
+        "ACO" => Box::new(Aco {
+            num_ants: args.aco_ants,
+            alpha: args.aco_alpha,
+            beta: args.aco_beta,
+            rho: args.aco_rho,
+            update: args.aco_update,
+        }),
+        "constructive" => Box::new(Constructive {
+            tau1: args.tau1,
+            tau2: args.tau2,
+        }),
+        "GRASP" => Box::new(Grasp {
+            tau1: args.tau1,
+            tau2: args.tau2,
+            alpha: args.grasp_alpha,
+        }),
+        "EDA" => Box::new(build_eda(args)),
+        "GA" => Box::new(build_ga(args)),
+        "VNS" => Box::new(Vns {
+            neighborhoods: args.vns_neighborhoods.clone(),
+            perturbation: args.vns_perturbation,
+            restart_after: args.vns_restart_after,
+        }),
+        _ => solver::by_name(name).unwrap(),
+    };
+
+    if args.threads > 1 {
+        Box::new(MultiStart {
+            solver,
+            threads: args.threads,
+        })
+    } else {
+        solver
+    }
+}
+
+fn build_ga(args: &Args) -> Ga {
+    Ga {
+        pop_size: args.ga_pop_size,
+        crossover: args.ga_crossover,
+        crossover_prob: args.ga_crossover_prob,
+        mutation: args.ga_mutation,
+        mutation_prob: args.ga_mutation_prob,
+        tournament_size: args.ga_tournament_size,
+        elitism: args.ga_elitism,
+        steady_state: args.ga_steady_state,
+    }
+}
+
+fn build_eda(args: &Args) -> Eda {
+    Eda {
+        model: args.eda_model,
+        ..Default::default()
+    }
+}
+
+/// A solution read from the `--apply` file, which must be a feasible permutation.
+fn imported(
+    name: &str,
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    solution: &[usize],
+) -> Solved {
+    let mut seen = vec![false; problem.n];
+    let is_permutation = solution.len() == problem.n
+        && solution
+            .iter()
+            .all(|item| *item < problem.n && !std::mem::replace(&mut seen[*item], true));
+    if !is_permutation || !problem.constraints.is_feasible(solution) {
+        fatal_error(
+            format!("The solution of `{name}` to apply is not a feasible permutation").as_str(),
+        );
+    }
+
+    Solved {
+        solution: solution.to_vec(),
+        fitness: objective.eval(problem, solution),
+        algorithm: "Imported".to_string(),
+        front: None,
+        bound: None,
+        evaluations: 1,
+        millis: 0,
+    }
+}
+
+/// Runs the selected algorithm. Multi-objective algorithms also return the Pareto front the
+/// solution was picked from.
+fn run_algorithm(
+    args: &Args,
+    name: &str,
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    time: &TimeShare,
+    messages: &Messages,
+) -> Solved {
+    let run = new_run(args, name, problem, objective, time, messages);
+
+    match &args.algorithm {
+        Algorithm::Solver(solver_name) => {
+            let solver = build_solver(args, solver_name);
+            let result = solver.solve(problem, objective, run);
+            Solved {
+                solution: result.solution,
+                fitness: result.fitness,
+                algorithm: solver_name.clone(),
+                front: None,
+                bound: None,
+                evaluations: result.evaluations,
+                millis: result.millis,
+            }
+        }
+        Algorithm::Nsga2 => {
+            let mut run = run;
+            let maximize = Maximize(objective);
+            let spread = HotSpread::default();
+            let criteria: [&dyn Criterion; 4] =
+                [&maximize, &TakenBranches, &InsertedJumps, &spread];
+
+            let nsga2 = Nsga2 {
+                pop_size: args.nsga2_pop_size,
+                mutation_prob: args.nsga2_mutation_prob,
+            };
+            let front = nsga2.run(problem, &criteria, &mut run);
+            let chosen = match front.select(&args.selection) {
+                Ok(idx) => &front.points[idx],
+                Err(e) => fatal_error(format!("Cannot select a solution: {e}").as_str()),
+            };
+
+            // the first criterion is the objective, and finishing gives the time left back to
+            // the pool
+            let result = run.finish(chosen.solution.clone(), chosen.values[0]);
+            Solved {
+                solution: result.solution,
+                fitness: result.fitness,
+                algorithm: "NSGA2".to_string(),
+                bound: None,
+                evaluations: result.evaluations,
+                millis: result.millis,
+                front: Some(front),
+            }
+        }
+    }
+}
+
+/// Prints the values of the solutions of a Pareto front, marking the selected one.
+fn print_front(front: &ParetoFront, selected: &[usize]) {
+    println!("  * Pareto front ({}):", front.criteria.join(", "));
+    for point in front.points.iter() {
+        let mark = if point.solution == selected { '*' } else { ' ' };
+        println!("    {mark} {:?}", point.values);
+    }
+    println!();
+}
+
+fn write_fronts(args: &Args, fronts: &HashMap<String, ParetoFront>) {
+    if let Some(path) = &args.pareto_front {
+        if let Err(e) = fs::write(path, serde_json::to_string(fronts).unwrap()) {
+            fatal_error(format!("Cannot write the Pareto fronts to `{path}`: {e}").as_str());
+        }
+    }
+}
+
+/// Prints the fitness of the identity and the optimized solution under all the objectives.
+fn print_report(
+    name: &str,
+    args: &Args,
+    objectives: &[&dyn Objective],
+    problem: &CoProblem,
+    cache: &CacheSim,
+    solved: &Solved,
+) {
+    let (opt_sol, opt_fitness) = (&solved.solution, solved.fitness);
+    let identity = (0..problem.n).collect::<Vec<usize>>();
+    let iden_fitness = objectives[args.objective as usize].eval(problem, &identity);
+
+    println!("{name}");
+
+    if opt_fitness <= iden_fitness {
+        println!("*** Function not optimized ***");
+    }
+
+    println!("  * Fitness of identity: {}", iden_fitness);
+    println!("  * {} fitness: {}", solved.algorithm, opt_fitness);
+    println!(
+        "  * Evaluations (time): {} ({} ms)",
+        solved.evaluations, solved.millis
+    );
+    if let Some(bound) = solved.bound {
+        println!(
+            "  * Upper bound of the optimum (gap): {} ({})",
+            bound,
+            bound.saturating_sub(opt_fitness)
+        );
+    }
+    for other in objectives.iter() {
+        println!(
+            "  * {} (identity -> optimized): {} -> {}",
+            other.name(),
+            other.eval(problem, &identity),
+            other.eval(problem, opt_sol)
+        );
+    }
+    let (iden_stats, opt_stats) = (
+        cache.simulate(problem, &identity),
+        cache.simulate(problem, opt_sol),
+    );
+    println!(
+        "  * i-cache misses (identity -> optimized): {} -> {}",
+        iden_stats.icache_misses, opt_stats.icache_misses
+    );
+    println!(
+        "  * iTLB misses (identity -> optimized): {} -> {}",
+        iden_stats.itlb_misses, opt_stats.itlb_misses
+    );
+    println!();
+    if let Some(front) = &solved.front {
+        print_front(front, opt_sol);
+    }
+    if args.verbosity > 1 {
+        println!("  * Optimized solution: {:?}", opt_sol);
+    }
+}
+
+/// Prints the fitness of the current layout (the identity) under all the objectives, and its
+/// cache simulation results.
+fn print_evaluation(
+    name: &str,
+    objectives: &[&dyn Objective],
+    problem: &CoProblem,
+    cache: &CacheSim,
+) {
+    let identity = (0..problem.n).collect::<Vec<usize>>();
+
+    println!("{name}");
+    for objective in objectives.iter() {
+        println!(
+            "  * {}: {}",
+            objective.name(),
+            objective.eval(problem, &identity)
+        );
+    }
+
+    let stats = cache.simulate(problem, &identity);
+    println!(
+        "  * i-cache misses: {} / {} accesses",
+        stats.icache_misses, stats.icache_accesses
+    );
+    println!(
+        "  * iTLB misses: {} / {} accesses",
+        stats.itlb_misses, stats.itlb_accesses
+    );
+    println!();
 }
@@ -0,0 +1,135 @@
+use clap::Parser;
+use rand::prelude::*;
+
+use pgo_co::{
+    co::synthetic::{self, SyntheticConfig},
+    fatal_error,
+};
+
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Generate a CO problem instance of random functions with structured control flow, to
+/// benchmark the algorithms without profiled programs
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the output file. If not provided, the output is
+    /// dumped to stdout
+    #[clap(short, long)]
+    out_path: Option<String>,
+
+    /// Number of functions of the instance, named `synthetic_0`, `synthetic_1`, ...
+    #[clap(short = 'n', long, default_value_t = 20)]
+    num_funcs: usize,
+
+    /// Minimum number of basic blocks of a function
+    #[clap(long, default_value_t = 10)]
+    min_bb_num: usize,
+
+    /// Maximum number of basic blocks of a function
+    #[clap(long, default_value_t = 100)]
+    max_bb_num: usize,
+
+    /// Seed of the random number generator. The same seed and parameters always generate the
+    /// same instance
+    #[clap(short, long, default_value_t = 0)]
+    seed: u64,
+
+    /// Probability of each statement being a loop
+    #[clap(long, default_value_t = SyntheticConfig::default().loop_prob)]
+    loop_prob: f64,
+
+    /// Probability of each statement being an if-then(-else) diamond
+    #[clap(long, default_value_t = SyntheticConfig::default().diamond_prob)]
+    diamond_prob: f64,
+
+    /// Probability of each statement being a switch
+    #[clap(long, default_value_t = SyntheticConfig::default().switch_prob)]
+    switch_prob: f64,
+
+    /// Probability of each statement being a check with a cold error path
+    #[clap(long, default_value_t = SyntheticConfig::default().error_prob)]
+    error_prob: f64,
+
+    /// Maximum nesting level of loops, diamonds and switches
+    #[clap(long, default_value_t = SyntheticConfig::default().max_depth)]
+    max_depth: usize,
+
+    /// Largest number of cases of a switch
+    #[clap(long, default_value_t = SyntheticConfig::default().max_switch_cases)]
+    max_switch_cases: usize,
+
+    /// Mean number of iterations of a loop each time it is entered
+    #[clap(long, default_value_t = SyntheticConfig::default().mean_trip_count)]
+    mean_trip_count: f64,
+
+    /// Probability that a two-way branch takes one of its sides at least 90% of the times
+    #[clap(long, default_value_t = SyntheticConfig::default().bias_prob)]
+    bias_prob: f64,
+
+    /// Mean fraction of the times a check takes its error path
+    #[clap(long, default_value_t = SyntheticConfig::default().mean_error_rate)]
+    mean_error_rate: f64,
+
+    /// Mean number of instructions of a basic block
+    #[clap(long, default_value_t = SyntheticConfig::default().mean_block_size)]
+    mean_block_size: f64,
+
+    /// Mean number of times a function is entered
+    #[clap(long, default_value_t = SyntheticConfig::default().mean_entry_count)]
+    mean_entry_count: f64,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.min_bb_num == 0 || args.max_bb_num < args.min_bb_num {
+        fatal_error("The number of basic blocks must satisfy 0 < min-bb-num <= max-bb-num");
+    }
+    let probs = [
+        args.loop_prob,
+        args.diamond_prob,
+        args.switch_prob,
+        args.error_prob,
+        args.bias_prob,
+    ];
+    if probs.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        fatal_error("Probabilities must be in [0, 1]");
+    }
+
+    let config = SyntheticConfig {
+        loop_prob: args.loop_prob,
+        diamond_prob: args.diamond_prob,
+        switch_prob: args.switch_prob,
+        error_prob: args.error_prob,
+        max_depth: args.max_depth,
+        max_switch_cases: args.max_switch_cases,
+        mean_trip_count: args.mean_trip_count,
+        bias_prob: args.bias_prob,
+        mean_error_rate: args.mean_error_rate,
+        mean_block_size: args.mean_block_size,
+        mean_entry_count: args.mean_entry_count,
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let problem_set = (0..args.num_funcs)
+        .map(|i| {
+            let num_blocks = rng.gen_range(args.min_bb_num..=args.max_bb_num);
+            (
+                format!("synthetic_{i}"),
+                synthetic::random_problem(&config, num_blocks, &mut rng),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let out = serde_json::to_string(&problem_set).unwrap();
+    match &args.out_path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, out) {
+                fatal_error(format!("Cannot write the instance to `{path}`: {e}").as_str());
+            }
+        }
+        None => println!("{out}"),
+    }
+}
@@ -0,0 +1,259 @@
+use rand::prelude::*;
+
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::solver::{Run, Solver};
+use super::{CoProblem, Objective};
+
+/// How the ants lay pheromone after each iteration, once the trails evaporate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PheromoneUpdate {
+    /// Max-min ant system: only the best ant of the iteration lays pheromone, and the trails are
+    /// kept between `1 / rho` (the limit of a trail reinforced at every iteration) and
+    /// `1 / (2 n rho)`. The trails start at the maximum.
+    MaxMin,
+    /// Rank-based elitist ant system: the best `k - 1` ants of the iteration lay `k - r`, where
+    /// `r` is their rank (from 1), and the best solution found lays `k`.
+    Elite(usize),
+}
+
+impl FromStr for PheromoneUpdate {
+    type Err = String;
+
+    /// Parses `max-min` or `elite:K`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "max-min" {
+            return Ok(PheromoneUpdate::MaxMin);
+        }
+
+        match s.strip_prefix("elite:") {
+            Some(k) => match k.trim().parse::<usize>() {
+                Ok(k) if k > 0 => Ok(PheromoneUpdate::Elite(k)),
+                _ => Err(format!("Invalid number of elite ants {k}")),
+            },
+            None => Err(format!(
+                "Invalid pheromone update {s}. Valid options are: max-min and elite:K"
+            )),
+        }
+    }
+}
+
+/// Ant colony optimization. Each ant builds a layout item after item, picking the next item `j`
+/// after `i` with probability proportional to `tau[i][j]^alpha * eta[i][j]^beta`, where `tau`
+/// is the pheromone and `eta` is `1 + w / mean`, `w` being the interaction of `i` and `j` and
+/// `mean` the mean interaction of the interacting pairs. Only the items that keep the layout
+/// feasible are picked while there is one.
+pub struct Aco {
+    pub num_ants: usize,
+    /// Exponent of the pheromone
+    pub alpha: f64,
+    /// Exponent of the heuristic desirability
+    pub beta: f64,
+    /// Fraction of the pheromone that evaporates at each iteration
+    pub rho: f64,
+    pub update: PheromoneUpdate,
+}
+
+impl Default for Aco {
+    fn default() -> Self {
+        Aco {
+            num_ants: 20,
+            alpha: 1.0,
+            beta: 2.0,
+            rho: 0.1,
+            update: PheromoneUpdate::MaxMin,
+        }
+    }
+}
+
+/// Pheromone, or any other value, of each transition. Row `n` holds the transitions from the
+/// start of the layout to its first item.
+struct Transitions {
+    n: usize,
+    values: Vec<f64>,
+}
+
+impl Transitions {
+    fn new(n: usize, value: f64) -> Self {
+        Transitions {
+            n,
+            values: vec![value; (n + 1) * n],
+        }
+    }
+
+    fn row(&self, from: Option<usize>) -> &[f64] {
+        let from = from.unwrap_or(self.n);
+        &self.values[from * self.n..(from + 1) * self.n]
+    }
+
+    /// Adds `amount` to each transition of `solution`.
+    fn lay(&mut self, solution: &[usize], amount: f64) {
+        let mut from = self.n;
+        for item in solution {
+            self.values[from * self.n + item] += amount;
+            from = *item;
+        }
+    }
+}
+
+impl Aco {
+    /// A new solution built by an ant, and its fitness.
+    fn construct(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        weights: &Transitions,
+        rng: &mut impl Rng,
+    ) -> (Vec<usize>, u128) {
+        let n = problem.n;
+        let mut solution = Vec::with_capacity(n);
+        let mut placed = vec![false; n];
+
+        for pos in 0..n {
+            let last = solution.last().copied();
+            let row = weights.row(last);
+
+            let allowed = (0..n)
+                .filter(|j| {
+                    !placed[*j]
+                        && problem
+                            .constraints
+                            .allows_next(pos, last, *j, |a| placed[a])
+                })
+                .collect::<Vec<usize>>();
+            // at a dead end any item goes, and the solution is repaired at the end
+            let candidates = if allowed.is_empty() {
+                (0..n).filter(|j| !placed[*j]).collect()
+            } else {
+                allowed
+            };
+
+            let total: f64 = candidates.iter().map(|j| row[*j]).sum();
+            let next = if total > 0.0 && total.is_finite() {
+                // roulette wheel
+                let mut r = rng.gen::<f64>() * total;
+                *candidates
+                    .iter()
+                    .find(|j| {
+                        r -= row[**j];
+                        r <= 0.0
+                    })
+                    .unwrap_or_else(|| candidates.last().unwrap())
+            } else {
+                *candidates.choose(rng).unwrap()
+            };
+
+            solution.push(next);
+            placed[next] = true;
+        }
+
+        let solution = problem.constraints.repair(&solution);
+        let f = objective.eval(problem, &solution);
+        (solution, f)
+    }
+}
+
+impl Solver for Aco {
+    fn name(&self) -> &'static str {
+        "ACO"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let n = problem.n;
+
+        let mut eta = Transitions::new(n, 1.0);
+        let (mut w_sum, mut w_count) = (0.0, 0);
+        for i in 0..n {
+            for &(_, w) in problem.c.neighbours(i) {
+                w_sum += w as f64;
+                w_count += 1;
+            }
+        }
+        let mean = if w_count > 0 {
+            w_sum / w_count as f64
+        } else {
+            1.0
+        };
+        for i in 0..n {
+            for &(j, w) in problem.c.neighbours(i) {
+                eta.values[i * n + j] = 1.0 + w as f64 / mean;
+            }
+        }
+
+        let tau_max = 1.0 / self.rho;
+        let tau_min = tau_max / (2 * n.max(1)) as f64;
+        let mut tau = match self.update {
+            PheromoneUpdate::MaxMin => Transitions::new(n, tau_max),
+            PheromoneUpdate::Elite(_) => Transitions::new(n, 1.0),
+        };
+
+        let mut best: (Vec<usize>, u128) = (vec![], 0);
+        let mut weights = Transitions::new(n, 0.0);
+
+        loop {
+            for (w, (t, e)) in weights
+                .values
+                .iter_mut()
+                .zip(tau.values.iter().zip(eta.values.iter()))
+            {
+                *w = t.powf(self.alpha) * e.powf(self.beta);
+            }
+
+            let mut ants = Vec::with_capacity(self.num_ants);
+            for _ in 0..self.num_ants.max(1) {
+                let ant = self.construct(problem, objective, &weights, &mut run.rng);
+                if best.0.is_empty() || ant.1 > best.1 {
+                    best = ant.clone();
+                }
+                run.evaluated(1, best.1);
+                ants.push(ant);
+
+                if run.is_over() {
+                    break;
+                }
+            }
+            if run.is_over() {
+                break;
+            }
+
+            // best ants first
+            ants.sort_by_key(|a| Reverse(a.1));
+
+            tau.values.iter_mut().for_each(|t| *t *= 1.0 - self.rho);
+            match self.update {
+                PheromoneUpdate::MaxMin => {
+                    tau.lay(&ants[0].0, 1.0);
+                    tau.values
+                        .iter_mut()
+                        .for_each(|t| *t = t.clamp(tau_min, tau_max));
+                }
+                PheromoneUpdate::Elite(k) => {
+                    for (r, (ant, _)) in ants.iter().enumerate().take(k - 1) {
+                        tau.lay(ant, (k - 1 - r) as f64);
+                    }
+                    tau.lay(&best.0, k as f64);
+                }
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("ants", self.num_ants);
+            log::set_attr("alpha", self.alpha);
+            log::set_attr("beta", self.beta);
+            log::set_attr("rho", self.rho);
+            log::set_attr("pheromone update", format!("{:?}", self.update));
+        }
+
+        best
+    }
+}
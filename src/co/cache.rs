@@ -0,0 +1,343 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+
+use std::str::FromStr;
+
+use super::{CoProblem, Objective};
+
+/// Geometry of a set-associative cache with LRU replacement.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    /// Size in bytes of each block of the cache (the line size of a cache, or the page size of
+    /// a TLB)
+    pub line_size: u64,
+    pub num_sets: usize,
+    pub ways: usize,
+}
+
+/// Instruction cache and instruction TLB of the simulated machine.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheModel {
+    pub icache: CacheConfig,
+    pub itlb: CacheConfig,
+    /// Cost (e.g. cycles) of an i-cache miss
+    pub icache_miss_cost: u64,
+    /// Cost of an iTLB miss
+    pub itlb_miss_cost: u64,
+    /// Number of bytes of each size unit of the problem, e.g. `1` if the sizes are in bytes
+    pub unit_bytes: u64,
+}
+
+impl Default for CacheModel {
+    /// A 32 KiB, 8-way i-cache with 64 byte lines and a 64 entry, 4-way iTLB with 4 KiB pages.
+    fn default() -> Self {
+        CacheModel {
+            icache: CacheConfig {
+                line_size: 64,
+                num_sets: 64,
+                ways: 8,
+            },
+            itlb: CacheConfig {
+                line_size: 4096,
+                num_sets: 16,
+                ways: 4,
+            },
+            icache_miss_cost: 10,
+            itlb_miss_cost: 30,
+            unit_bytes: 1,
+        }
+    }
+}
+
+/// How the execution traces are generated from the interactions of the problem, which are
+/// taken as the transition counts of a Markov chain.
+#[derive(Debug, Clone, Copy)]
+pub enum TraceKind {
+    /// Random walks that start at an entry item (an item with more outgoing than incoming
+    /// weight) and restart when they reach an item without successors.
+    RandomWalk,
+    /// Independent samples of the stationary distribution of the chain (the independent
+    /// reference model): only how often each item is executed is taken into account, not the
+    /// order.
+    Stationary,
+}
+
+impl FromStr for TraceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "walk" => Ok(TraceKind::RandomWalk),
+            "stationary" => Ok(TraceKind::Stationary),
+            _ => Err(format!(
+                "Invalid trace kind {s}. Valid options are: walk and stationary"
+            )),
+        }
+    }
+}
+
+/// Result of replaying a trace against a `CacheModel`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub icache_accesses: u64,
+    pub icache_misses: u64,
+    pub itlb_accesses: u64,
+    pub itlb_misses: u64,
+}
+
+/// Objective that simulates the instruction cache and TLB behaviour of a layout.
+///
+/// A trace of executed items is generated once from the problem (see `TraceKind`) and, for
+/// each layout, every item of the trace accesses the cache lines and pages it spans, with the
+/// items placed one after the other from address zero. The fitness is the cost of the misses
+/// subtracted from the cost of the worst case, so maximizing it minimizes the misses.
+///
+/// The traces are specific to a problem: the objective must only be used with the problem it
+/// was built for.
+pub struct CacheSim {
+    pub model: CacheModel,
+    trace: Vec<usize>,
+    /// Fitness of the layout in which every access misses
+    worst_cost: u128,
+    /// Upper bound of the fitness of any layout: the distinct items of the trace take at least
+    /// as many lines and pages as their total size, and each of them misses at least once
+    bound: u128,
+}
+
+impl CacheSim {
+    /// Builds the objective for `problem`, generating a trace of `trace_len` items with the
+    /// given random seed.
+    pub fn new(
+        problem: &CoProblem,
+        model: CacheModel,
+        kind: TraceKind,
+        trace_len: usize,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let trace = match kind {
+            TraceKind::RandomWalk => random_walk_trace(problem, trace_len, &mut rng),
+            TraceKind::Stationary => stationary_trace(problem, trace_len, &mut rng),
+        };
+
+        // an item of `b > 0` bytes spans at most `ceil(b / line) + 1` lines
+        let max_blocks = |bytes: u64, config: &CacheConfig| match bytes {
+            0 => 0,
+            _ => (bytes - 1) / config.line_size + 2,
+        };
+        let worst_cost = trace
+            .iter()
+            .map(|item| {
+                let bytes = problem.s[*item] as u64 * model.unit_bytes;
+                max_blocks(bytes, &model.icache) as u128 * model.icache_miss_cost as u128
+                    + max_blocks(bytes, &model.itlb) as u128 * model.itlb_miss_cost as u128
+            })
+            .sum();
+
+        let mut traced = vec![false; problem.n];
+        for item in trace.iter() {
+            traced[*item] = true;
+        }
+        let traced_bytes = (0..problem.n)
+            .filter(|item| traced[*item])
+            .map(|item| problem.s[item] as u64 * model.unit_bytes)
+            .sum::<u64>();
+        let compulsory_cost = traced_bytes.div_ceil(model.icache.line_size) as u128
+            * model.icache_miss_cost as u128
+            + traced_bytes.div_ceil(model.itlb.line_size) as u128 * model.itlb_miss_cost as u128;
+
+        CacheSim {
+            model,
+            trace,
+            worst_cost,
+            bound: worst_cost - compulsory_cost,
+        }
+    }
+
+    pub fn trace(&self) -> &[usize] {
+        &self.trace
+    }
+
+    /// Replays the trace with the items placed as in `solution`.
+    pub fn simulate(&self, problem: &CoProblem, solution: &[usize]) -> CacheStats {
+        let mut start = vec![0; problem.n];
+        let mut addr = 0;
+        for item in solution {
+            start[*item] = addr;
+            addr += problem.s[*item] as u64 * self.model.unit_bytes;
+        }
+
+        let mut icache = Cache::new(&self.model.icache);
+        let mut itlb = Cache::new(&self.model.itlb);
+        let mut stats = CacheStats::default();
+
+        for item in self.trace.iter() {
+            let bytes = problem.s[*item] as u64 * self.model.unit_bytes;
+            if bytes == 0 {
+                continue;
+            }
+            let (first, last) = (start[*item], start[*item] + bytes - 1);
+
+            for page in (first / itlb.block_size)..=(last / itlb.block_size) {
+                stats.itlb_accesses += 1;
+                stats.itlb_misses += !itlb.access(page) as u64;
+            }
+            for line in (first / icache.block_size)..=(last / icache.block_size) {
+                stats.icache_accesses += 1;
+                stats.icache_misses += !icache.access(line) as u64;
+            }
+        }
+
+        stats
+    }
+}
+
+impl Objective for CacheSim {
+    fn name(&self) -> &'static str {
+        "cache"
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        let stats = self.simulate(problem, solution);
+        self.worst_cost
+            - stats.icache_misses as u128 * self.model.icache_miss_cost as u128
+            - stats.itlb_misses as u128 * self.model.itlb_miss_cost as u128
+    }
+
+    fn max_fitness(&self, _problem: &CoProblem) -> Option<u128> {
+        Some(self.worst_cost)
+    }
+
+    /// Only the compulsory misses of the trace are left out, a bound that the layouts which place
+    /// the items of the trace first reach whenever they fit in the cache.
+    fn bound_prefix(&self, _problem: &CoProblem, _prefix: &[usize]) -> u128 {
+        self.bound
+    }
+}
+
+/// Set-associative cache with LRU replacement, addressed by block number.
+struct Cache {
+    block_size: u64,
+    num_sets: usize,
+    ways: usize,
+    /// `num_sets * ways` entries of `(tag, last access time)`, `None` if empty
+    entries: Vec<Option<(u64, u64)>>,
+    time: u64,
+}
+
+impl Cache {
+    fn new(config: &CacheConfig) -> Self {
+        Cache {
+            block_size: config.line_size,
+            num_sets: config.num_sets,
+            ways: config.ways,
+            entries: vec![None; config.num_sets * config.ways],
+            time: 0,
+        }
+    }
+
+    /// Accesses the given block, returns `true` if it was a hit.
+    fn access(&mut self, block: u64) -> bool {
+        self.time += 1;
+
+        let set = (block % self.num_sets as u64) as usize;
+        let entries = &mut self.entries[set * self.ways..(set + 1) * self.ways];
+
+        if let Some(entry) = entries.iter_mut().flatten().find(|(tag, _)| *tag == block) {
+            entry.1 = self.time;
+            return true;
+        }
+
+        // replace an empty entry, or the least recently used one
+        let victim = entries
+            .iter_mut()
+            .min_by_key(|e| e.map(|(_, time)| time).unwrap_or(0))
+            .unwrap();
+        *victim = Some((block, self.time));
+
+        false
+    }
+}
+
+/// Entry points of the chain: the items with more outgoing than incoming weight, weighted by
+/// the difference. If there's none, all the items weighted by their outgoing weight.
+fn entry_weights(problem: &CoProblem) -> Vec<u64> {
+    let mut incoming = vec![0; problem.n];
+    for (_, b, w) in problem.c.edges() {
+        incoming[b] += w;
+    }
+
+    let weights = (0..problem.n)
+        .map(|a| problem.c.row_sum(a).saturating_sub(incoming[a]))
+        .collect::<Vec<u64>>();
+
+    if weights.iter().any(|w| *w > 0) {
+        weights
+    } else {
+        (0..problem.n).map(|a| problem.c.row_sum(a)).collect()
+    }
+}
+
+fn random_walk_trace(problem: &CoProblem, len: usize, rng: &mut StdRng) -> Vec<usize> {
+    let entries = match WeightedIndex::new(entry_weights(problem)) {
+        Ok(dist) => dist,
+        // no interactions at all
+        Err(_) => return vec![],
+    };
+    let successors = (0..problem.n)
+        .map(|a| {
+            let succs = problem.c.successors(a);
+            WeightedIndex::new(succs.iter().map(|(_, w)| *w))
+                .ok()
+                .map(|dist| (succs, dist))
+        })
+        .collect::<Vec<_>>();
+
+    let mut trace = Vec::with_capacity(len);
+    let mut current = entries.sample(rng);
+    while trace.len() < len {
+        trace.push(current);
+        current = match &successors[current] {
+            Some((succs, dist)) => succs[dist.sample(rng)].0,
+            None => entries.sample(rng),
+        };
+    }
+
+    trace
+}
+
+fn stationary_trace(problem: &CoProblem, len: usize, rng: &mut StdRng) -> Vec<usize> {
+    let entries = entry_weights(problem);
+    let entries_sum: u64 = entries.iter().sum();
+    if entries_sum == 0 {
+        return vec![];
+    }
+
+    // power iteration of the lazy chain (which has the same stationary distribution but can't
+    // be periodic), where the items without successors go back to the entries
+    let mut dist = entries
+        .iter()
+        .map(|w| *w as f64 / entries_sum as f64)
+        .collect::<Vec<f64>>();
+    for _ in 0..200 {
+        let mut next = dist.iter().map(|p| p / 2.0).collect::<Vec<f64>>();
+        let mut restart = 0.0;
+        for (a, p) in dist.iter().enumerate() {
+            let row_sum = problem.c.row_sum(a);
+            if row_sum == 0 {
+                restart += p / 2.0;
+                continue;
+            }
+            for (b, w) in problem.c.successors(a) {
+                next[*b] += p / 2.0 * *w as f64 / row_sum as f64;
+            }
+        }
+        for (p, w) in next.iter_mut().zip(entries.iter()) {
+            *p += restart * *w as f64 / entries_sum as f64;
+        }
+        dist = next;
+    }
+
+    let dist = WeightedIndex::new(dist).unwrap();
+    (0..len).map(|_| dist.sample(rng)).collect()
+}
@@ -0,0 +1,505 @@
+use serde::{Deserialize, Serialize, Serializer};
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::layout::{min_max, Layout};
+
+/// Constraints on the permutations that are valid solutions of a `CoProblem`.
+///
+/// * Pinned items: `(item, position)`, the item must be placed at the given position.
+/// * Precedences: `(a, b)`, item `a` must be placed (anywhere) before item `b`.
+/// * Adjacencies: `(a, b)`, item `b` must be placed right after item `a`.
+///
+/// The algorithms of `co::*` only generate solutions that satisfy the constraints: the initial
+/// solutions are made feasible with `repair`, and moves are filtered with `allows_swap` and
+/// `allows_insert`.
+#[derive(Clone, Debug, Default)]
+pub struct Constraints {
+    lists: ConstraintLists,
+    /// Feasible solution to fall back to when `repair` can't fix a solution
+    fallback: Vec<usize>,
+    /// `true` for the items that can't be moved alone: pinned items and the items of chains of
+    /// adjacent items
+    fixed: Vec<bool>,
+    /// `pinned_before[k]` is the number of pinned positions below `k`
+    pinned_before: Vec<usize>,
+    /// `succ[a]` is the item that must be placed right after `a`, if any
+    succ: Vec<Option<usize>>,
+    /// `after[a]` are the items that must be placed after `a`
+    after: Vec<Vec<usize>>,
+    /// `before[b]` are the items that must be placed before `b`
+    before: Vec<Vec<usize>>,
+}
+
+/// The constraints as listed in the instance files.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(super) struct ConstraintLists {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pinned: Vec<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    precedence: Vec<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    adjacent: Vec<(usize, usize)>,
+}
+
+impl Constraints {
+    /// Builds the constraints of a problem with `n` items. Returns an error if some item is out
+    /// of bounds or the constraints can't be satisfied.
+    pub fn new(
+        n: usize,
+        pinned: Vec<(usize, usize)>,
+        precedence: Vec<(usize, usize)>,
+        adjacent: Vec<(usize, usize)>,
+    ) -> Result<Self, String> {
+        Self::from_lists(
+            n,
+            ConstraintLists {
+                pinned,
+                precedence,
+                adjacent,
+            },
+        )
+    }
+
+    pub(super) fn from_lists(n: usize, lists: ConstraintLists) -> Result<Self, String> {
+        let out_of_bounds = lists
+            .pinned
+            .iter()
+            .chain(lists.precedence.iter())
+            .chain(lists.adjacent.iter())
+            .any(|(a, b)| *a >= n || *b >= n);
+        if out_of_bounds {
+            return Err(format!("constraint out of bounds for {n} items"));
+        }
+
+        let mut fixed = vec![false; n];
+
+        let mut pinned_pos = vec![false; n];
+        for (item, pos) in lists.pinned.iter() {
+            fixed[*item] = true;
+            pinned_pos[*pos] = true;
+        }
+        let mut pinned_before = vec![0; n + 1];
+        for k in 0..n {
+            pinned_before[k + 1] = pinned_before[k] + pinned_pos[k] as usize;
+        }
+
+        let mut succ = vec![None; n];
+        let mut has_pred = vec![false; n];
+        for (a, b) in lists.adjacent.iter() {
+            if succ[*a].replace(*b).is_some() || has_pred[*b] {
+                return Err(format!("item {a} or {b} has more than one adjacent item"));
+            }
+            has_pred[*b] = true;
+            fixed[*a] = true;
+            fixed[*b] = true;
+        }
+
+        let mut after = vec![vec![]; n];
+        let mut before = vec![vec![]; n];
+        for (a, b) in lists.precedence.iter() {
+            after[*a].push(*b);
+            before[*b].push(*a);
+        }
+
+        let mut constraints = Constraints {
+            lists,
+            fallback: vec![],
+            fixed,
+            pinned_before,
+            succ,
+            after,
+            before,
+        };
+
+        let identity = (0..n).collect::<Vec<usize>>();
+        constraints.fallback = constraints
+            .build_feasible(&identity, false)
+            .or_else(|| constraints.build_feasible(&identity, true))
+            .ok_or("the constraints can't be satisfied")?;
+
+        Ok(constraints)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.pinned.is_empty()
+            && self.lists.precedence.is_empty()
+            && self.lists.adjacent.is_empty()
+    }
+
+    pub fn pinned(&self) -> &[(usize, usize)] {
+        &self.lists.pinned
+    }
+
+    pub fn precedence(&self) -> &[(usize, usize)] {
+        &self.lists.precedence
+    }
+
+    pub fn adjacent(&self) -> &[(usize, usize)] {
+        &self.lists.adjacent
+    }
+
+    /// Returns `true` if `order` satisfies all the constraints.
+    pub fn is_feasible(&self, order: &[usize]) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let mut pos = vec![0; order.len()];
+        for (k, item) in order.iter().enumerate() {
+            pos[*item] = k;
+        }
+
+        self.lists.pinned.iter().all(|(item, p)| pos[*item] == *p)
+            && self.lists.precedence.iter().all(|(a, b)| pos[*a] < pos[*b])
+            && self
+                .lists
+                .adjacent
+                .iter()
+                .all(|(a, b)| pos[*b] == pos[*a] + 1)
+    }
+
+    /// Returns a feasible solution that keeps the relative order of the items of `order` as much
+    /// as the constraints allow.
+    pub fn repair(&self, order: &[usize]) -> Vec<usize> {
+        if self.is_feasible(order) {
+            return order.to_vec();
+        }
+        self.build_feasible(order, false)
+            .or_else(|| self.build_feasible(order, true))
+            .unwrap_or_else(|| self.fallback.clone())
+    }
+
+    /// Returns `true` if swapping the items at positions `i` and `j` of the (feasible) `layout`
+    /// keeps it feasible.
+    pub fn allows_swap(&self, layout: &Layout, i: usize, j: usize) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let (i, j) = min_max(i, j);
+        let (x, y) = (layout.order[i], layout.order[j]);
+        if self.fixed[x] || self.fixed[y] {
+            return false;
+        }
+
+        // `x` moves forward to `j` and `y` backward to `i`
+        self.after[x].iter().all(|s| layout.pos[*s] > j)
+            && self.before[y].iter().all(|p| layout.pos[*p] < i)
+    }
+
+    /// Returns `true` if moving the item at position `from` of the (feasible) `layout` to
+    /// position `to` keeps it feasible.
+    pub fn allows_insert(&self, layout: &Layout, from: usize, to: usize) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let z = layout.order[from];
+        if self.fixed[z] {
+            return false;
+        }
+
+        // the items in between shift one position
+        let (lo, hi) = min_max(from, to);
+        if self.pinned_before[hi + 1] - self.pinned_before[lo] > 0 {
+            return false;
+        }
+
+        // the items in between keep their relative order, but `z` gets in the middle of the pair
+        // that surrounds its new position
+        let (left, right) = if from < to {
+            (to, to + 1)
+        } else {
+            (to.wrapping_sub(1), to)
+        };
+        if let (Some(l), Some(r)) = (layout.order.get(left), layout.order.get(right)) {
+            if self.succ[*l] == Some(*r) {
+                return false;
+            }
+        }
+
+        if from < to {
+            self.after[z].iter().all(|s| layout.pos[*s] > to)
+        } else {
+            self.before[z].iter().all(|p| layout.pos[*p] < to)
+        }
+    }
+
+    /// Returns `true` if moving the `len` items starting at position `start` of the (feasible)
+    /// `layout` so that they start at position `to` keeps it feasible.
+    pub fn allows_move_segment(
+        &self,
+        layout: &Layout,
+        start: usize,
+        len: usize,
+        to: usize,
+    ) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        // the segment swaps places with the block of items it jumps over, `lo..mid` is the block
+        // on the left and `mid..hi` the one on the right. All of their items change of position
+        let (lo, mid, hi) = if start < to {
+            (start, start + len, to + len)
+        } else {
+            (to, start, start + len)
+        };
+        if self.pinned_before[hi] - self.pinned_before[lo] > 0 {
+            return false;
+        }
+
+        // the blocks keep their inner order, but the pairs around their borders are split
+        if [lo, mid, hi].iter().any(|k| self.splits(layout, *k)) {
+            return false;
+        }
+
+        // and the items of the segment change sides with the items of the other block
+        if start < to {
+            (start..start + len).all(|p| {
+                self.after[layout.order[p]]
+                    .iter()
+                    .all(|s| !(mid..hi).contains(&layout.pos[*s]))
+            })
+        } else {
+            (start..start + len).all(|p| {
+                self.before[layout.order[p]]
+                    .iter()
+                    .all(|b| !(lo..mid).contains(&layout.pos[*b]))
+            })
+        }
+    }
+
+    /// Returns `true` if reversing the items at positions `i..=j` of the (feasible) `layout`
+    /// keeps it feasible.
+    pub fn allows_reverse(&self, layout: &Layout, i: usize, j: usize) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        // only the item in the middle of a segment of odd length keeps its position
+        let (i, j) = min_max(i, j);
+        let pinned = self.pinned_before[j + 1] - self.pinned_before[i];
+        let mid = (i + j) / 2;
+        let mid_pinned = (j - i) % 2 == 0 && self.pinned_before[mid + 1] > self.pinned_before[mid];
+        if pinned > mid_pinned as usize {
+            return false;
+        }
+
+        // every pair of adjacent items inside the segment and around it is split
+        if (i..=j + 1).any(|k| self.splits(layout, k)) {
+            return false;
+        }
+
+        // and the items of the segment reverse their relative order
+        (i..=j).all(|p| {
+            self.after[layout.order[p]]
+                .iter()
+                .all(|s| !(i..=j).contains(&layout.pos[*s]))
+        })
+    }
+
+    /// Returns `true` if the items at positions `k - 1` and `k` of `layout` must be adjacent, so
+    /// a move can't place anything in between.
+    fn splits(&self, layout: &Layout, k: usize) -> bool {
+        k > 0 && k < layout.order.len() && self.succ[layout.order[k - 1]] == Some(layout.order[k])
+    }
+
+    /// Returns `true` if `item` can be placed at position `pos`, right after `last`, given the
+    /// items already placed at positions `0..pos` (`is_placed`). Only the constraints that can be
+    /// decided from those items are checked, so some of the prefixes it accepts may not have any
+    /// feasible completion.
+    pub fn allows_next(
+        &self,
+        pos: usize,
+        last: Option<usize>,
+        item: usize,
+        is_placed: impl Fn(usize) -> bool,
+    ) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let pinned_ok = self
+            .lists
+            .pinned
+            .iter()
+            .all(|(i, p)| (*i == item) == (*p == pos));
+        let adjacent_ok = last.and_then(|l| self.succ[l]).unwrap_or(item) == item
+            && self
+                .lists
+                .adjacent
+                .iter()
+                .all(|(a, b)| *b != item || Some(*a) == last);
+
+        pinned_ok && adjacent_ok && self.before[item].iter().all(|a| is_placed(*a))
+    }
+
+    /// Greedily builds a feasible solution, placing first the items that come first in `order`.
+    /// Chains of adjacent items are placed as a unit, and pinned units at their position.
+    /// Returns `None` if it doesn't find any.
+    ///
+    /// If `by_deadline` is set, the units that have to be placed the soonest (because of the
+    /// pinned units that must come after them) go first, regardless of `order`.
+    fn build_feasible(&self, order: &[usize], by_deadline: bool) -> Option<Vec<usize>> {
+        let n = self.succ.len();
+
+        let mut rank = vec![0; n];
+        for (k, item) in order.iter().enumerate() {
+            rank[*item] = k;
+        }
+
+        // split the items into chains of adjacent items
+        let mut has_pred = vec![false; n];
+        self.succ.iter().flatten().for_each(|b| has_pred[*b] = true);
+
+        let mut units: Vec<Vec<usize>> = vec![];
+        let mut unit_of = vec![usize::MAX; n];
+        let mut offset = vec![0; n];
+        for head in (0..n).filter(|i| !has_pred[*i]) {
+            let mut unit = vec![head];
+            while let Some(next) = self.succ[*unit.last().unwrap()] {
+                unit.push(next);
+            }
+            for (k, item) in unit.iter().enumerate() {
+                unit_of[*item] = units.len();
+                offset[*item] = k;
+            }
+            units.push(unit);
+        }
+        // items in a cycle of adjacencies
+        if unit_of.contains(&usize::MAX) {
+            return None;
+        }
+
+        // position where each pinned unit must start
+        let mut start_at: Vec<Option<usize>> = vec![None; n];
+        let mut pinned_start = vec![None; units.len()];
+        for (item, pos) in self.lists.pinned.iter() {
+            let u = unit_of[*item];
+            let start = pos.checked_sub(offset[*item])?;
+            if start + units[u].len() > n
+                || pinned_start[u].unwrap_or(start) != start
+                || start_at[start].unwrap_or(u) != u
+            {
+                return None;
+            }
+            pinned_start[u] = Some(start);
+            start_at[start] = Some(u);
+        }
+
+        // precedences between units
+        let mut unit_succs = vec![vec![]; units.len()];
+        let mut num_preds = vec![0; units.len()];
+        for (a, b) in self.lists.precedence.iter() {
+            let (ua, ub) = (unit_of[*a], unit_of[*b]);
+            if ua == ub {
+                if offset[*a] >= offset[*b] {
+                    return None;
+                }
+            } else {
+                unit_succs[ua].push(ub);
+                num_preds[ub] += 1;
+            }
+        }
+
+        // latest position where each unit can start, in reverse topological order
+        let mut deadline = (0..units.len())
+            .map(|u| pinned_start[u].unwrap_or(n - units[u].len()))
+            .collect::<Vec<usize>>();
+        for u in Self::topological_order(&unit_succs, &num_preds)?
+            .iter()
+            .rev()
+        {
+            for v in unit_succs[*u].iter() {
+                deadline[*u] = deadline[*u].min(deadline[*v].checked_sub(units[*u].len())?);
+            }
+        }
+
+        let priority = |u: usize| {
+            let rank = units[u].iter().map(|i| rank[*i]).min().unwrap();
+            if by_deadline {
+                (deadline[u], rank)
+            } else {
+                (0, rank)
+            }
+        };
+
+        // free units ready to be placed, by priority
+        let mut ready = BinaryHeap::new();
+        for u in 0..units.len() {
+            if num_preds[u] == 0 && pinned_start[u].is_none() {
+                ready.push(Reverse((priority(u), u)));
+            }
+        }
+
+        let mut solution = Vec::with_capacity(n);
+        while solution.len() < n {
+            let k = solution.len();
+
+            let unit = match start_at[k] {
+                Some(u) if num_preds[u] == 0 => u,
+                Some(_) => return None,
+                None => {
+                    // free space until the next pinned unit
+                    let space = (k..n).find(|p| start_at[*p].is_some()).unwrap_or(n) - k;
+
+                    let mut skipped = vec![];
+                    let mut found = None;
+                    while let Some(Reverse((p, u))) = ready.pop() {
+                        if units[u].len() <= space {
+                            found = Some(u);
+                            break;
+                        }
+                        skipped.push(Reverse((p, u)));
+                    }
+                    ready.extend(skipped);
+                    found?
+                }
+            };
+
+            solution.extend(units[unit].iter());
+            for next in unit_succs[unit].iter() {
+                num_preds[*next] -= 1;
+                if num_preds[*next] == 0 && pinned_start[*next].is_none() {
+                    ready.push(Reverse((priority(*next), *next)));
+                }
+            }
+        }
+
+        Some(solution)
+    }
+
+    /// Returns the nodes of a DAG in topological order, or `None` if the graph has a cycle.
+    fn topological_order(succs: &[Vec<usize>], num_preds: &[usize]) -> Option<Vec<usize>> {
+        let mut num_preds = num_preds.to_vec();
+        let mut order = (0..succs.len())
+            .filter(|u| num_preds[*u] == 0)
+            .collect::<Vec<usize>>();
+
+        let mut k = 0;
+        while k < order.len() {
+            for v in succs[order[k]].iter() {
+                num_preds[*v] -= 1;
+                if num_preds[*v] == 0 {
+                    order.push(*v);
+                }
+            }
+            k += 1;
+        }
+
+        if order.len() == succs.len() {
+            Some(order)
+        } else {
+            None
+        }
+    }
+}
+
+impl Serialize for Constraints {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.lists.serialize(serializer)
+    }
+}
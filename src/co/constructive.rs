@@ -1,54 +1,102 @@
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, Rng};
 
 use std::collections::VecDeque;
 
-use super::CoProblem;
+use super::solver::{Run, Solver};
+use super::{CoProblem, Objective};
 
 #[cfg(feature = "log")]
 use crate::log;
 
-pub fn construct_solution(problem: &CoProblem, tau1: u32, tau2: u32) -> (Vec<usize>, u64) {
+/// Randomized greedy construction, from the most interacting items and their successors.
+pub struct Constructive {
+    /// Exponent of the weights of the items when picking the next one
+    pub tau1: u32,
+    /// Exponent of the weights of the successors of the picked item
+    pub tau2: u32,
+}
+
+impl Default for Constructive {
+    fn default() -> Self {
+        Constructive { tau1: 3, tau2: 2 }
+    }
+}
+
+impl Solver for Constructive {
+    fn name(&self) -> &'static str {
+        "constructive"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let s = construct_solution(problem, self.tau1, self.tau2, 1.0, &mut run.rng);
+        let fitness = objective.eval(problem, &s);
+        run.evaluated(1, fitness);
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("tau1", self.tau1);
+            log::set_attr("tau2", self.tau2);
+        }
+
+        (s, fitness)
+    }
+}
+
+/// Builds a feasible solution. Each item is picked at random, weighted by `tau1` and `tau2`, from
+/// a restricted candidate list: the candidates whose weight is at least
+/// `max - alpha * (max - min)`, so `alpha = 1` considers all of them and `alpha = 0` only the
+/// heaviest ones.
+pub(super) fn construct_solution(
+    problem: &CoProblem,
+    tau1: u32,
+    tau2: u32,
+    alpha: f64,
+    rng: &mut impl Rng,
+) -> Vec<usize> {
     let mut solution = VecDeque::with_capacity(problem.n);
 
     let max_size = problem.s.iter().sum::<usize>() as u64;
-    let mut rng = rand::thread_rng();
 
     let mut push_front = false;
 
-    let mut ordered_by_interaction_tmp = problem
-        .c
-        .iter()
-        .enumerate()
-        .collect::<Vec<(usize, &Vec<u64>)>>();
+    let mut ordered_by_interaction = (0..problem.n)
+        .map(|i| (i, problem.c.row_sum(i)))
+        .collect::<Vec<(usize, u64)>>();
 
-    ordered_by_interaction_tmp.sort_by(|a, b| {
-        a.1.iter()
-            .sum::<u64>()
-            .cmp(&b.1.iter().sum::<u64>())
-            .reverse()
-    });
+    ordered_by_interaction.sort_by(|a, b| a.1.cmp(&b.1).reverse());
 
-    let ord_by_interact = ordered_by_interaction_tmp
-        .iter()
-        .map(|(i, _)| *i)
-        .collect::<Vec<usize>>();
+    // `rank[item]` is the position of `item` in the items sorted by decreasing interaction
+    let mut rank = vec![0; problem.n];
+    for (r, (item, _)) in ordered_by_interaction.iter().enumerate() {
+        rank[*item] = r;
+    }
 
     while solution.len() < problem.n {
         // get the interaction list and size of the non selected items
         let non_selected = (0..problem.n)
             .filter(|idx| !solution.contains(idx))
-            .map(|idx| (idx, problem.c[idx].as_slice(), problem.s[idx]))
-            .collect::<Vec<(usize, &[u64], usize)>>();
+            .map(|idx| (idx, problem.c.successors(idx), problem.s[idx]))
+            .collect::<Vec<(usize, &[(usize, u64)], usize)>>();
 
-        // get the weigths of the non selected items
+        // get the weigths of the non selected items, the larger the more they interact
         // list of: (weight, (index, c_list))
         let non_selected_with_weights = non_selected
             .iter()
-            .map(|(idx, c, _)| (ord_by_interact[*idx].pow(tau1), (*idx, *c)))
-            .collect::<Vec<(usize, (usize, &[u64]))>>();
+            .map(|(idx, c, _)| ((problem.n - rank[*idx]).pow(tau1), (*idx, *c)))
+            .collect::<Vec<(usize, (usize, &[(usize, u64)]))>>();
+        let min_weight = restriction(non_selected_with_weights.iter().map(|s| s.0), alpha);
+        let non_selected_with_weights = non_selected_with_weights
+            .into_iter()
+            .filter(|s| s.0 >= min_weight)
+            .collect::<Vec<(usize, (usize, &[(usize, u64)]))>>();
 
         let (_, parent) = non_selected_with_weights
-            .choose_weighted(&mut rng, |s| s.0 + 1)
+            .choose_weighted(rng, |s| s.0 + 1)
             .unwrap();
 
         if push_front {
@@ -63,16 +111,15 @@ pub fn construct_solution(problem: &CoProblem, tau1: u32, tau2: u32) -> (Vec<usi
         let mut children = parent
             .1
             .iter()
-            .enumerate()
-            .filter(|(idx, v)| **v > 0 && !solution.contains(idx))
+            .filter(|(idx, _)| !solution.contains(idx))
             // also consider call frequency from child to parent
-            .map(|(child_id, v)| (child_id, v + problem.c[child_id][parent.0]))
+            .map(|(child_id, v)| (*child_id, v + problem.c.get(*child_id, parent.0)))
             .collect::<Vec<(usize, u64)>>();
 
         // sort children by: children_size*calls_parent2child
         children.sort_by(|(a_idx, a_c), (b_idx, b_c)| {
-            let a = a_c * (max_size - problem.s[*a_idx] as u64);
-            let b = b_c * (max_size - problem.s[*b_idx] as u64);
+            let a = *a_c as u128 * (max_size - problem.s[*a_idx] as u64) as u128;
+            let b = *b_c as u128 * (max_size - problem.s[*b_idx] as u64) as u128;
             a.cmp(&b).reverse()
         });
 
@@ -82,12 +129,14 @@ pub fn construct_solution(problem: &CoProblem, tau1: u32, tau2: u32) -> (Vec<usi
             .collect::<Vec<(usize, usize)>>();
 
         while !children_weights.is_empty() {
+            let min_weight = restriction(children_weights.iter().map(|(_, w)| *w), alpha);
             let (sel_idx, (child_index, _w)) = children_weights
                 .iter()
                 .map(|(id, w)| (*id, *w))
                 .enumerate()
+                .filter(|(_, (_, w))| *w >= min_weight)
                 .collect::<Vec<(usize, (usize, usize))>>()
-                .choose_weighted(&mut rng, |s| s.1 .1)
+                .choose_weighted(rng, |s| s.1 .1)
                 .map(|v| *v)
                 .unwrap();
 
@@ -103,16 +152,39 @@ pub fn construct_solution(problem: &CoProblem, tau1: u32, tau2: u32) -> (Vec<usi
     }
 
     let s: Vec<usize> = solution.into();
-    let fitness = problem.eval(&s);
-
-    #[cfg(feature = "log")]
-    {
-        log::set_attr("algorithm", "constructive");
-        log::set_attr("tau1", tau1);
-        log::set_attr("tau2", tau2);
-        log::set_attr("best fitness", fitness);
-        log::write();
-    }
+    problem.constraints.repair(&s)
+}
 
-    (s, fitness)
+/// Smallest weight of the restricted candidate list of the given weights.
+fn restriction(weights: impl Iterator<Item = usize> + Clone, alpha: f64) -> usize {
+    let max = weights.clone().max().unwrap_or(0);
+    let min = weights.min().unwrap_or(0);
+    max - (alpha * (max - min) as f64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::co::{Constraints, Interactions};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn greedy_without_restriction() {
+        // no item has successors, so the items are picked one by one by decreasing interaction,
+        // and placed at the back and the front alternately
+        let n = 5;
+        let interactions = [3, 40, 7, 25, 1];
+        let problem = CoProblem {
+            c: Interactions::from_edges(n, (0..n).map(|i| (i, i, interactions[i]))),
+            s: vec![1; n],
+            n,
+            constraints: Constraints::default(),
+        };
+
+        for seed in 0..10 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let solution = construct_solution(&problem, 3, 2, 0.0, &mut rng);
+            assert_eq!(solution, vec![0, 3, 1, 2, 4]);
+        }
+    }
 }
@@ -0,0 +1,215 @@
+use rand::Rng;
+
+use std::str::FromStr;
+
+/// A crossover operator over permutations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossover {
+    Order,
+    PartiallyMapped,
+    Cycle,
+}
+
+impl FromStr for Crossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ox" => Ok(Crossover::Order),
+            "pmx" => Ok(Crossover::PartiallyMapped),
+            "cx" => Ok(Crossover::Cycle),
+            _ => Err(format!(
+                "Invalid crossover {s}. Valid options are: ox, pmx and cx"
+            )),
+        }
+    }
+}
+
+impl Crossover {
+    /// A child of `p1` and `p2`, which must be permutations of the same length.
+    pub fn apply(self, p1: &[usize], p2: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+        match self {
+            Crossover::Order => order_crossover(p1, p2, rng),
+            Crossover::PartiallyMapped => partially_mapped_crossover(p1, p2, rng),
+            Crossover::Cycle => cycle_crossover(p1, p2),
+        }
+    }
+}
+
+/// Order crossover (OX): the child takes a random slice of `p1` at the same positions, and the
+/// rest of the items in the order they appear in `p2`, starting after the slice.
+pub fn order_crossover(p1: &[usize], p2: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let n = p1.len();
+    if n < 2 {
+        return p1.to_vec();
+    }
+
+    let (a, b) = random_slice(n, rng);
+
+    let mut taken = vec![false; n];
+    let mut child = vec![usize::MAX; n];
+    for k in a..=b {
+        child[k] = p1[k];
+        taken[p1[k]] = true;
+    }
+
+    let mut k = (b + 1) % n;
+    for item in p2.iter().cycle().skip(b + 1).take(n) {
+        if !taken[*item] {
+            child[k] = *item;
+            k = (k + 1) % n;
+        }
+    }
+
+    child
+}
+
+/// Partially mapped crossover (PMX): the child takes a random slice of `p1` at the same
+/// positions, and the rest of the positions from `p2`. The items of `p2` already in the slice are
+/// replaced following the mapping between the slices of both parents.
+pub fn partially_mapped_crossover(p1: &[usize], p2: &[usize], rng: &mut impl Rng) -> Vec<usize> {
+    let n = p1.len();
+    if n < 2 {
+        return p1.to_vec();
+    }
+
+    let (a, b) = random_slice(n, rng);
+
+    // position of each item of the slice in `p1`
+    let mut slice_pos = vec![None; n];
+    for k in a..=b {
+        slice_pos[p1[k]] = Some(k);
+    }
+
+    let mut child = p2.to_vec();
+    child[a..=b].copy_from_slice(&p1[a..=b]);
+    for k in (0..a).chain((b + 1)..n) {
+        let mut item = p2[k];
+        // the chain ends as `p2` has no repeated items
+        while let Some(pos) = slice_pos[item] {
+            item = p2[pos];
+        }
+        child[k] = item;
+    }
+
+    child
+}
+
+/// Cycle crossover (CX): the positions are split in the cycles of the mapping between both
+/// parents, and the child takes the items of the cycles alternately from `p1` and `p2`, so every
+/// item keeps the position it has in one of the parents.
+pub fn cycle_crossover(p1: &[usize], p2: &[usize]) -> Vec<usize> {
+    let n = p1.len();
+
+    let mut pos1 = vec![0; n];
+    for (k, item) in p1.iter().enumerate() {
+        pos1[*item] = k;
+    }
+
+    let mut child = vec![usize::MAX; n];
+    let mut from_p1 = true;
+    for start in 0..n {
+        if child[start] != usize::MAX {
+            continue;
+        }
+
+        let parent = if from_p1 { p1 } else { p2 };
+        let mut k = start;
+        loop {
+            child[k] = parent[k];
+            k = pos1[p2[k]];
+            if k == start {
+                break;
+            }
+        }
+        from_p1 = !from_p1;
+    }
+
+    child
+}
+
+/// Returns the bounds `(a, b)`, with `a <= b`, of a random slice of a solution of length `n`.
+fn random_slice(n: usize, rng: &mut impl Rng) -> (usize, usize) {
+    let a = rng.gen_range(0..n);
+    let b = rng.gen_range(0..n);
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    /// Calls `check` with random parents of several lengths and the rng the child is made with.
+    fn for_parents(mut check: impl FnMut(&[usize], &[usize], &mut StdRng)) {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for n in [0, 1, 2, 3, 8, 20] {
+            for _ in 0..20 {
+                let mut p1 = (0..n).collect::<Vec<usize>>();
+                let mut p2 = p1.clone();
+                p1.shuffle(&mut rng);
+                p2.shuffle(&mut rng);
+                check(&p1, &p2, &mut rng);
+            }
+        }
+    }
+
+    fn assert_permutation(child: &[usize]) {
+        let mut sorted = child.to_vec();
+        sorted.sort_unstable();
+        assert!(
+            sorted.iter().copied().eq(0..child.len()),
+            "{:?} isn't a permutation",
+            child
+        );
+    }
+
+    #[test]
+    fn partially_mapped_keeps_positions() {
+        for_parents(|p1, p2, rng| {
+            // the crossover draws the same slice first
+            let (a, b) = random_slice(p1.len().max(1), &mut rng.clone());
+            let child = partially_mapped_crossover(p1, p2, rng);
+            assert_permutation(&child);
+            if p1.len() < 2 {
+                return;
+            }
+
+            // the slice comes from `p1`, and the rest from `p2` unless the item is in the slice
+            assert_eq!(child[a..=b], p1[a..=b]);
+            for k in (0..a).chain(b + 1..p1.len()) {
+                if !p1[a..=b].contains(&p2[k]) {
+                    assert_eq!(child[k], p2[k]);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn cycle_keeps_positions() {
+        for_parents(|p1, p2, _| {
+            let child = cycle_crossover(p1, p2);
+            assert_permutation(&child);
+            for k in 0..child.len() {
+                assert!(child[k] == p1[k] || child[k] == p2[k]);
+            }
+        });
+    }
+
+    #[test]
+    fn order_keeps_slice() {
+        for_parents(|p1, p2, rng| {
+            let (a, b) = random_slice(p1.len().max(1), &mut rng.clone());
+            let child = order_crossover(p1, p2, rng);
+            assert_permutation(&child);
+            if p1.len() >= 2 {
+                assert_eq!(child[a..=b], p1[a..=b]);
+            }
+        });
+    }
+}
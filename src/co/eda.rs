@@ -1,56 +1,200 @@
-use rand::{distributions::*, seq::SliceRandom};
+use rand::{distributions::*, rngs::StdRng, seq::SliceRandom, Rng};
 
-use std::time::Instant;
+use std::str::FromStr;
 
 #[cfg(feature = "log")]
 use crate::log;
 
-use super::CoProblem;
-use crate::MAX_OPT_MILLIS;
+use super::parallel::{Evolutionary, Individual};
+use super::solver::{Run, Solver};
+use super::{CoProblem, Objective};
 
 struct Umd(Vec<Vec<usize>>);
 struct Population(Vec<Vec<usize>>);
 
-pub fn run(problem: &CoProblem, pop_size: usize, num_select: usize) -> (Vec<usize>, u64) {
-    let time = Instant::now();
-    let mut pop = Population::init(problem.n, pop_size);
+/// Largest spread parameter learnt for a Mallows model. Without a bound, a selection of equal
+/// solutions would only ever sample their consensus.
+const MAX_SPREAD: f64 = 10.0;
 
-    let mut best_f = 0;
-    let mut best_sol: Vec<usize> = vec![];
+/// Number of iterations of the MM algorithm that learns the weights of a Plackett-Luce model
+const PL_ITERS: usize = 20;
 
-    #[cfg(feature = "log")]
-    let mut evals = 0;
+/// Estimation of distribution algorithm: at each iteration, the rest of the population is
+/// replaced by samples of a model learnt from its best `num_select` solutions.
+pub struct Eda {
+    pub pop_size: usize,
+    /// Number of solutions the distribution is learnt from at each iteration
+    pub num_select: usize,
+    pub model: Model,
+}
+
+impl Default for Eda {
+    fn default() -> Self {
+        Eda {
+            pop_size: 300,
+            num_select: 100,
+            model: Model::Umd,
+        }
+    }
+}
+
+/// Distance between permutations of a Mallows model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Distance {
+    /// Number of pairs of items in different relative order
+    Kendall,
+    /// Minimum number of swaps that turn one permutation into the other
+    Cayley,
+}
+
+/// Probabilistic model of an `Eda`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// Marginal distribution of the items at each position (UMDA).
+    Umd,
+    /// Probability that decreases exponentially with the distance to a consensus permutation.
+    Mallows(Distance),
+    /// Items drawn one position after another with probability proportional to their weight.
+    PlackettLuce,
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "umd" => Ok(Model::Umd),
+            "mallows-kendall" => Ok(Model::Mallows(Distance::Kendall)),
+            "mallows-cayley" => Ok(Model::Mallows(Distance::Cayley)),
+            "plackett-luce" => Ok(Model::PlackettLuce),
+            _ => Err(format!(
+                "Invalid model {s}. Valid options are: umd, mallows-kendall, mallows-cayley and \
+                 plackett-luce"
+            )),
+        }
+    }
+}
 
-    loop {
-        if MAX_OPT_MILLIS <= time.elapsed().as_millis() {
-            break;
+impl Model {
+    /// Learns the model from the selected solutions.
+    fn learn(self, selected: &Vec<&Vec<usize>>) -> Box<dyn PermutationModel> {
+        match self {
+            Model::Umd => Box::new(Umd::from(selected)),
+            Model::Mallows(distance) => Box::new(Mallows::learn(selected, distance)),
+            Model::PlackettLuce => Box::new(PlackettLuce::learn(selected)),
         }
+    }
+}
 
-        let best_sol_info = pop.select_survivors(problem, num_select);
+/// A probability distribution over permutations.
+trait PermutationModel {
+    /// Replaces the solutions of `out` at the given indexes with samples of the distribution.
+    fn sample_and_replace(&self, out: &mut Population, indexes: &[usize], rng: &mut StdRng);
+}
+
+impl Solver for Eda {
+    fn name(&self) -> &'static str {
+        "EDA"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut pop = self.init(problem, objective, run);
+
+        loop {
+            if run.is_over() {
+                break;
+            }
+            self.generation(problem, objective, &mut pop, run);
+        }
+
+        #[cfg(feature = "log")]
+        log::set_attr("model", format!("{:?}", self.model));
+
+        pop.best
+    }
+}
+
+/// Population of an `Eda`, the solutions selected in the last iteration and the best solution
+/// found.
+pub struct EdaPopulation {
+    pop: Population,
+    /// Index and fitness of the selected solutions, best first
+    selected: Vec<(usize, u128)>,
+    /// Indexes of the solutions sampled in the last iteration
+    sampled: Vec<usize>,
+    best: (Vec<usize>, u128),
+}
+
+impl Evolutionary for Eda {
+    type Population = EdaPopulation;
+
+    /// The initial population is evaluated, so that there is a best solution even if the budget
+    /// is over before the first iteration.
+    fn init(&self, problem: &CoProblem, objective: &dyn Objective, run: &mut Run) -> EdaPopulation {
+        let mut pop = Population::init(problem.n, self.pop_size, &mut run.rng);
+        pop.repair(problem, &(0..self.pop_size).collect::<Vec<usize>>());
+
+        let best = match pop.select_survivors(problem, objective, 1).first() {
+            Some((i, f)) => (pop.0[*i].clone(), *f),
+            None => {
+                let identity = problem
+                    .constraints
+                    .repair(&(0..problem.n).collect::<Vec<_>>());
+                let f = objective.eval(problem, &identity);
+                (identity, f)
+            }
+        };
+        run.evaluated(self.pop_size.max(1), best.1);
+
+        EdaPopulation {
+            pop,
+            selected: vec![],
+            sampled: vec![],
+            best,
+        }
+    }
+
+    fn generation(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        pop: &mut EdaPopulation,
+        run: &mut Run,
+    ) {
+        let (pop_size, num_select) = (self.pop_size, self.num_select);
+        let EdaPopulation {
+            pop,
+            selected,
+            sampled,
+            best,
+        } = pop;
+
+        let best_sol_info = pop.select_survivors(problem, objective, num_select);
 
         let (iter_best_idx, iter_best_f) = best_sol_info
             .iter()
             .max_by(|(_, a), (_, b)| a.cmp(b))
             .unwrap();
 
-        if *iter_best_f > best_f {
-            best_sol = pop.0[*iter_best_idx].clone();
-            best_f = *iter_best_f;
+        if *iter_best_f > best.1 {
+            *best = (pop.0[*iter_best_idx].clone(), *iter_best_f);
         }
 
+        run.evaluated(pop_size, best.1);
         #[cfg(feature = "log")]
         {
-            evals += pop_size;
-            log::log("time", time.elapsed().as_millis());
-            log::log("evaluation", evals);
-            log::log("best fitness", best_f);
             log::log("pop size", pop_size);
             log::log("num select", num_select);
         }
 
         // indexes of the non selected solutions (worsts)
         let worsts_index = (0..pop_size)
-            .filter(|i| best_sol_info.iter().find(|(j, _)| i == j).is_some())
+            .filter(|i| best_sol_info.iter().all(|(j, _)| i != j))
             .collect::<Vec<usize>>();
 
         let bests = best_sol_info
@@ -58,45 +202,70 @@ pub fn run(problem: &CoProblem, pop_size: usize, num_select: usize) -> (Vec<usiz
             .map(|(i, _)| &pop.0[*i])
             .collect::<Vec<&Vec<usize>>>();
 
-        let distrib = Umd::from(&bests);
+        let distrib = self.model.learn(&bests);
 
-        distrib.sample_and_replace(&mut pop, &worsts_index);
+        distrib.sample_and_replace(pop, &worsts_index, &mut run.rng);
+        pop.repair(problem, &worsts_index);
+
+        *selected = best_sol_info;
+        *sampled = worsts_index;
     }
 
-    #[cfg(feature = "log")]
-    {
-        log::set_attr("algorithm", "EDA");
-        log::write();
+    fn best(&self, pop: &EdaPopulation) -> Individual {
+        pop.best.clone()
     }
 
-    (best_sol, best_f)
+    fn emigrants(&self, pop: &EdaPopulation, count: usize) -> Vec<Individual> {
+        pop.selected
+            .iter()
+            .take(count)
+            .map(|(i, f)| (pop.pop.0[*i].clone(), *f))
+            .collect()
+    }
+
+    /// The migrants replace samples of the last iteration, which have not been evaluated yet.
+    fn immigrate(&self, pop: &mut EdaPopulation, migrants: Vec<Individual>) {
+        for (i, (s, _)) in pop.sampled.iter().zip(migrants) {
+            pop.pop.0[*i] = s;
+        }
+    }
 }
 
 impl Population {
-    pub fn init(length: usize, pop_size: usize) -> Self {
-        let mut rng = rand::thread_rng();
+    pub fn init(length: usize, pop_size: usize, rng: &mut impl Rng) -> Self {
         let pop = (0..pop_size)
             .map(|_| {
                 let mut v = (0..length).collect::<Vec<usize>>();
-                v.shuffle(&mut rng);
+                v.shuffle(rng);
                 v
             })
             .collect::<Vec<Vec<usize>>>();
         Population(pop)
     }
 
+    /// Makes the solutions at the given indexes satisfy the constraints of the problem.
+    pub fn repair(&mut self, problem: &CoProblem, indexes: &[usize]) {
+        for i in indexes {
+            self.0[*i] = problem.constraints.repair(&self.0[*i]);
+        }
+    }
+
     #[allow(dead_code)]
     #[cfg(debug_assertions)]
-    pub fn stats(&self, iter: usize, problem: &CoProblem) {
+    pub fn stats(&self, iter: usize, problem: &CoProblem, objective: &dyn Objective) {
         let pop_size = self.0.len();
 
-        let f = self.0.iter().map(|s| problem.eval(s)).collect::<Vec<u64>>();
+        let f = self
+            .0
+            .iter()
+            .map(|s| objective.eval(problem, s))
+            .collect::<Vec<u128>>();
 
-        let f_sum: u64 = f.iter().sum();
+        let f_sum: u128 = f.iter().sum();
         let f_min = f.iter().min().unwrap();
         let f_max = f.iter().max().unwrap();
 
-        let mean = f_sum / pop_size as u64;
+        let mean = f_sum / pop_size as u128;
 
         println!("(debug mode) iter: {iter} -> ({f_min}, {mean}, {f_max})");
     }
@@ -104,16 +273,20 @@ impl Population {
     // First, the function evaluates all the solutions of the population.
     // Then, solutions are sorted based on their fitness value (descending order),
     // and the index and fitness value of the first `num_select` solutions is returned.
-    pub fn select_survivors(&self, problem: &CoProblem, num_select: usize) -> Vec<(usize, u64)> {
+    pub fn select_survivors(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        num_select: usize,
+    ) -> Vec<(usize, u128)> {
         let mut f = self
             .0
             .iter()
             .enumerate()
-            .map(|(i, s)| (i, problem.eval(s)))
-            .collect::<Vec<(usize, u64)>>();
+            .map(|(i, s)| (i, objective.eval(problem, s)))
+            .collect::<Vec<(usize, u128)>>();
 
-        // f.sort_by(|(_, s1), (_, s2)| s2.cmp(s1));
-        f.sort_by(|(_, s1), (_, s2)| s1.cmp(s2));
+        f.sort_by(|(_, s1), (_, s2)| s2.cmp(s1));
         // just return the first `num_select` solutions
         f.truncate(num_select);
         f
@@ -132,9 +305,10 @@ impl Umd {
         }
         Umd(d)
     }
+}
 
-    pub fn sample_and_replace(&self, out: &mut Population, indexes: &[usize]) {
-        let mut rng = rand::thread_rng();
+impl PermutationModel for Umd {
+    fn sample_and_replace(&self, out: &mut Population, indexes: &[usize], rng: &mut StdRng) {
         let n = self.0.len();
 
         // TODO: sort `index` for better cache performance?
@@ -143,11 +317,18 @@ impl Umd {
         let mut new_w = (0..n)
             .map(|i| (i, &self.0[0][i]))
             .collect::<Vec<(usize, &usize)>>();
-        let mut wi = WeightedIndex::new(vec![1usize; n]).unwrap();
 
         for sol_idx in indexes {
+            // the first position of every solution is sampled from its own marginal, uniform if
+            // no item was ever seen there
+            let mut wi = if self.0[0].iter().any(|w| *w != 0) {
+                WeightedIndex::new(&self.0[0]).unwrap()
+            } else {
+                WeightedIndex::new(vec![1usize; n]).unwrap()
+            };
+
             for pos in 0..n {
-                let v = wi.sample(&mut rng);
+                let v = wi.sample(rng);
                 out.0[*sol_idx][pos] = v;
                 sampled.push(v);
 
@@ -171,11 +352,405 @@ impl Umd {
                             .filter(|(i, _)| !sampled.contains(i))
                             .for_each(|v| v.1 = &1);
                     }
-                }
 
-                wi.update_weights(new_w.as_slice()).unwrap();
+                    wi.update_weights(new_w.as_slice()).unwrap();
+                }
             }
             sampled.clear();
         }
     }
 }
+
+/// Mallows model: the probability of a permutation is proportional to
+/// `exp(-spread * distance(permutation, consensus))`.
+struct Mallows {
+    consensus: Vec<usize>,
+    spread: f64,
+    distance: Distance,
+}
+
+impl Mallows {
+    /// Estimates the consensus of the selected solutions, and the spread that maximizes their
+    /// likelihood given that consensus.
+    fn learn(selected: &Vec<&Vec<usize>>, distance: Distance) -> Self {
+        let consensus = match distance {
+            Distance::Kendall => borda(selected),
+            Distance::Cayley => most_agreeing(selected),
+        };
+
+        // the likelihood is maximal when the expected distance to the consensus equals the mean
+        // distance of the selected solutions, and the expected distance decreases with the spread
+        let mean = selected
+            .iter()
+            .map(|s| distance.between(s, &consensus) as f64)
+            .sum::<f64>()
+            / selected.len() as f64;
+
+        let n = consensus.len();
+        let (mut lo, mut hi) = (0.0, MAX_SPREAD);
+        let spread = if distance.expected(n, hi) >= mean {
+            hi
+        } else if distance.expected(n, lo) <= mean {
+            lo
+        } else {
+            for _ in 0..50 {
+                let mid = (lo + hi) / 2.0;
+                if distance.expected(n, mid) > mean {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            (lo + hi) / 2.0
+        };
+
+        #[cfg(feature = "log")]
+        log::log("spread", spread);
+
+        Mallows {
+            consensus,
+            spread,
+            distance,
+        }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Vec<usize> {
+        let n = self.consensus.len();
+
+        // permutation of the positions of the consensus at the sampled distance from the identity
+        let perm = match self.distance {
+            Distance::Kendall => {
+                // `inversions[j]` items greater than `j` go before `j`, each with a weight of
+                // `exp(-spread)` relative to one less
+                let mut perm = Vec::with_capacity(n);
+                for j in (0..n).rev() {
+                    let inversions = truncated_geometric(n - 1 - j, self.spread, rng);
+                    perm.insert(inversions, j);
+                }
+                perm
+            }
+            Distance::Cayley => {
+                // each non-trivial swap of the Fisher-Yates shuffle adds one to the distance
+                let mut perm = (0..n).collect::<Vec<usize>>();
+                for j in 0..n.saturating_sub(1) {
+                    let choices = (n - 1 - j) as f64;
+                    let p_swap = choices / (choices + self.spread.exp());
+                    if rng.gen::<f64>() < p_swap {
+                        let k = rng.gen_range((j + 1)..n);
+                        perm.swap(j, k);
+                    }
+                }
+                perm
+            }
+        };
+
+        perm.iter().map(|k| self.consensus[*k]).collect()
+    }
+}
+
+impl PermutationModel for Mallows {
+    fn sample_and_replace(&self, out: &mut Population, indexes: &[usize], rng: &mut StdRng) {
+        for i in indexes {
+            out.0[*i] = self.sample(rng);
+        }
+    }
+}
+
+impl Distance {
+    fn between(self, a: &[usize], b: &[usize]) -> usize {
+        let n = a.len();
+        let mut pos_b = vec![0; n];
+        for (k, item) in b.iter().enumerate() {
+            pos_b[*item] = k;
+        }
+        // `a` relative to `b`, whose distance to the identity is the one between `a` and `b`
+        let perm = a.iter().map(|item| pos_b[*item]).collect::<Vec<usize>>();
+
+        match self {
+            Distance::Kendall => {
+                // inversions counted with a Fenwick tree of the values seen so far
+                let mut tree = vec![0; n + 1];
+                let mut inversions = 0;
+                for (seen, v) in perm.iter().enumerate() {
+                    let mut k = *v + 1;
+                    let mut not_greater = 0;
+                    while k > 0 {
+                        not_greater += tree[k];
+                        k &= k - 1;
+                    }
+                    inversions += seen - not_greater;
+
+                    let mut k = *v + 1;
+                    while k <= n {
+                        tree[k] += 1;
+                        k += k & k.wrapping_neg();
+                    }
+                }
+                inversions
+            }
+            Distance::Cayley => {
+                let mut visited = vec![false; n];
+                let mut cycles = 0;
+                for start in 0..n {
+                    if visited[start] {
+                        continue;
+                    }
+                    cycles += 1;
+                    let mut k = start;
+                    while !visited[k] {
+                        visited[k] = true;
+                        k = perm[k];
+                    }
+                }
+                n - cycles
+            }
+        }
+    }
+
+    /// Expected distance to the consensus of the samples of a Mallows model of permutations of
+    /// length `n` with the given spread. Both distances are sums of independent variables
+    /// (see `Mallows::sample`).
+    fn expected(self, n: usize, spread: f64) -> f64 {
+        match self {
+            // `sum_{k=2}^{n} 1 / (e^s - 1) - k / (e^(k s) - 1)`, whose limit at 0 is `n (n-1) / 4`
+            Distance::Kendall if spread < 1e-9 => (n * n.saturating_sub(1)) as f64 / 4.0,
+            Distance::Kendall => (2..=n)
+                .map(|k| 1.0 / spread.exp_m1() - k as f64 / (k as f64 * spread).exp_m1())
+                .sum(),
+            Distance::Cayley => (1..n).map(|k| k as f64 / (k as f64 + spread.exp())).sum(),
+        }
+    }
+}
+
+/// Random integer of `0..=max` with probability proportional to `exp(-spread * value)`, drawn
+/// by inverting its distribution function.
+fn truncated_geometric(max: usize, spread: f64, rng: &mut StdRng) -> usize {
+    if spread < 1e-9 {
+        return rng.gen_range(0..=max);
+    }
+    // probability of a value of at most `max` without the truncation
+    let tail = -(-spread * (max + 1) as f64).exp_m1();
+    let u = rng.gen::<f64>();
+    let v = -(1.0 - u * tail).ln() / spread;
+    (v as usize).min(max)
+}
+
+/// Approximation of the Kendall consensus (the permutation with the smallest summed distance to
+/// the solutions) that sorts the items by their mean position (Borda count).
+fn borda(selected: &Vec<&Vec<usize>>) -> Vec<usize> {
+    let n = selected[0].len();
+    let mut pos_sum = vec![0; n];
+    for s in selected {
+        for (k, item) in s.iter().enumerate() {
+            pos_sum[*item] += k;
+        }
+    }
+
+    let mut consensus = (0..n).collect::<Vec<usize>>();
+    consensus.sort_by_key(|item| (pos_sum[*item], *item));
+    consensus
+}
+
+/// Approximation of the Cayley consensus: the permutation that agrees with the solutions in the
+/// most positions, found by assigning greedily the most frequent items at each position. Every
+/// fixed point of a solution relative to the consensus is a cycle that shortens its distance.
+fn most_agreeing(selected: &Vec<&Vec<usize>>) -> Vec<usize> {
+    let n = selected[0].len();
+    let mut freq = vec![vec![0; n]; n];
+    for s in selected {
+        for (k, item) in s.iter().enumerate() {
+            freq[k][*item] += 1;
+        }
+    }
+
+    let mut pairs = (0..n)
+        .flat_map(|k| (0..n).map(move |item| (k, item)))
+        .collect::<Vec<(usize, usize)>>();
+    // most frequent first, and ties in a fixed order
+    pairs.sort_by_key(|(k, item)| (std::cmp::Reverse(freq[*k][*item]), *k, *item));
+
+    let mut consensus = vec![usize::MAX; n];
+    let mut placed = vec![false; n];
+    for (k, item) in pairs {
+        if consensus[k] == usize::MAX && !placed[item] {
+            consensus[k] = item;
+            placed[item] = true;
+        }
+    }
+    consensus
+}
+
+/// Plackett-Luce model: the items are drawn one position after another, each of the remaining
+/// ones with probability proportional to its weight.
+struct PlackettLuce {
+    weights: Vec<f64>,
+}
+
+impl PlackettLuce {
+    /// Maximum likelihood weights of the selected solutions, found with the MM algorithm of
+    /// Hunter (2004). The weights are kept above a small floor, so that the items that are
+    /// always placed last can still be drawn earlier.
+    fn learn(selected: &Vec<&Vec<usize>>) -> Self {
+        let n = selected[0].len();
+        let floor = 1e-3 / n as f64;
+
+        // number of times each item is drawn among two or more remaining items
+        let mut wins = vec![0.0; n];
+        for s in selected {
+            for item in s.iter().take(n.saturating_sub(1)) {
+                wins[*item] += 1.0;
+            }
+        }
+
+        let mut weights = vec![1.0 / n as f64; n];
+        for _ in 0..PL_ITERS {
+            // sum, over the draws each item takes part in, of the inverse of the total weight
+            // of the remaining items
+            let mut denom = vec![0.0; n];
+            for s in selected {
+                let mut remaining = s.iter().map(|item| weights[*item]).sum::<f64>();
+                let mut acc = 0.0;
+                for item in s.iter().take(n.saturating_sub(1)) {
+                    acc += 1.0 / remaining;
+                    denom[*item] += acc;
+                    remaining -= weights[*item];
+                }
+                // the last item takes part in all the draws
+                if let Some(last) = s.last() {
+                    denom[*last] += acc;
+                }
+            }
+
+            for (w, (wins, denom)) in weights.iter_mut().zip(wins.iter().zip(denom.iter())) {
+                *w = if *denom > 0.0 { wins / denom } else { 0.0 };
+            }
+            let total = weights.iter().sum::<f64>();
+            for w in weights.iter_mut() {
+                *w = (*w / total).max(floor);
+            }
+        }
+
+        PlackettLuce { weights }
+    }
+
+    fn sample(&self, rng: &mut StdRng) -> Vec<usize> {
+        // sorting by the log-weights perturbed with Gumbel noise draws from the model
+        let mut keys = self
+            .weights
+            .iter()
+            .enumerate()
+            .map(|(item, w)| {
+                let u = rng.gen::<f64>().max(f64::MIN_POSITIVE);
+                (w.ln() - (-u.ln()).ln(), item)
+            })
+            .collect::<Vec<(f64, usize)>>();
+        keys.sort_by(|a, b| b.0.total_cmp(&a.0));
+        keys.into_iter().map(|(_, item)| item).collect()
+    }
+}
+
+impl PermutationModel for PlackettLuce {
+    fn sample_and_replace(&self, out: &mut Population, indexes: &[usize], rng: &mut StdRng) {
+        for i in indexes {
+            out.0[*i] = self.sample(rng);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// All the permutations of `0..n`.
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        if n == 0 {
+            return vec![vec![]];
+        }
+        let mut perms = vec![];
+        for perm in permutations(n - 1) {
+            for k in 0..n {
+                let mut perm = perm.clone();
+                perm.insert(k, n - 1);
+                perms.push(perm);
+            }
+        }
+        perms
+    }
+
+    /// Distances computed by definition: the discordant pairs, and the swaps selection sort needs.
+    fn brute_force(distance: Distance, a: &[usize], b: &[usize]) -> usize {
+        let n = a.len();
+        match distance {
+            Distance::Kendall => {
+                let pos = |p: &[usize], item| p.iter().position(|x| *x == item).unwrap();
+                (0..n)
+                    .flat_map(|x| (x + 1..n).map(move |y| (x, y)))
+                    .filter(|&(x, y)| (pos(a, x) < pos(a, y)) != (pos(b, x) < pos(b, y)))
+                    .count()
+            }
+            Distance::Cayley => {
+                let mut a = a.to_vec();
+                let mut swaps = 0;
+                for k in 0..n {
+                    if a[k] != b[k] {
+                        let other = a.iter().position(|x| *x == b[k]).unwrap();
+                        a.swap(k, other);
+                        swaps += 1;
+                    }
+                }
+                swaps
+            }
+        }
+    }
+
+    #[test]
+    fn distance_between_matches_definition() {
+        for distance in [Distance::Kendall, Distance::Cayley] {
+            for n in 0..=5 {
+                let perms = permutations(n);
+                for a in &perms {
+                    for b in &perms {
+                        assert_eq!(
+                            distance.between(a, b),
+                            brute_force(distance, a, b),
+                            "{:?} between {:?} and {:?}",
+                            distance,
+                            a,
+                            b
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn expected_distance_matches_enumeration() {
+        for distance in [Distance::Kendall, Distance::Cayley] {
+            for n in 1..=6 {
+                let perms = permutations(n);
+                let identity = (0..n).collect::<Vec<usize>>();
+                for spread in [0.0, 1e-12, 0.1, 1.0, 3.0] {
+                    // expectation under `P(perm) ~ exp(-spread * d(perm, identity))`
+                    let (mut weighted, mut total) = (0.0, 0.0);
+                    for perm in &perms {
+                        let d = distance.between(perm, &identity) as f64;
+                        let p = (-spread * d).exp();
+                        weighted += p * d;
+                        total += p;
+                    }
+                    let expected = distance.expected(n, spread);
+                    assert!(
+                        (expected - weighted / total).abs() < 1e-6,
+                        "{:?} of {} with spread {}: {} instead of {}",
+                        distance,
+                        n,
+                        spread,
+                        expected,
+                        weighted / total
+                    );
+                }
+            }
+        }
+    }
+}
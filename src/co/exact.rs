@@ -0,0 +1,238 @@
+#[cfg(feature = "log")]
+use std::time::Instant;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::solver::Run;
+use super::{CoProblem, Objective};
+
+/// Largest problem solved by `held_karp`, as its memory grows with `2^n`.
+pub const MAX_DP_ITEMS: usize = 20;
+
+/// Solution found by an exact solver.
+#[derive(Debug, Clone)]
+pub struct ExactSolution {
+    pub solution: Vec<usize>,
+    pub fitness: u128,
+    /// Proven upper bound of the optimal fitness. Equal to `fitness` unless the search was
+    /// stopped by the time limit
+    pub bound: u128,
+}
+
+impl ExactSolution {
+    /// Distance from the fitness to the bound, `0` if the solution is a proven optimum.
+    pub fn gap(&self) -> u128 {
+        self.bound - self.fitness
+    }
+
+    pub fn is_optimal(&self) -> bool {
+        self.gap() == 0
+    }
+}
+
+/// Solves the problem under the interaction objective (see `CoProblem::eval`) with dynamic
+/// programming over the sets of placed items, in the style of Held-Karp.
+///
+/// The weight of the pairs that span an item only depends on the set of items placed before it
+/// (see `CoProblem::bound_prefix`), so the best placement of each set is computed from the best
+/// placements of its subsets. Returns `None` if the problem has more than `MAX_DP_ITEMS` items or
+/// adjacency constraints, which depend on the order of the set.
+pub fn held_karp(problem: &CoProblem) -> Option<ExactSolution> {
+    let n = problem.n;
+    if n > MAX_DP_ITEMS || !problem.constraints.adjacent().is_empty() {
+        return None;
+    }
+
+    #[cfg(feature = "log")]
+    let time = Instant::now();
+
+    let degree = (0..n)
+        .map(|a| {
+            problem
+                .c
+                .neighbours(a)
+                .iter()
+                .map(|(_, w)| *w as u128)
+                .sum()
+        })
+        .collect::<Vec<u128>>();
+
+    let full = (1usize << n) - 1;
+    // `cost[set]` is the least summed size times spanning weight of the items of `set` when they
+    // are placed first, and `last[set]` the item placed last in that placement. `cross[set]` is
+    // the summed weight of the pairs between `set` and the rest of items
+    let mut cost = vec![u128::MAX; full + 1];
+    let mut last = vec![0u8; full + 1];
+    let mut cross = vec![0u128; full + 1];
+    cost[0] = 0;
+
+    // every set is smaller than its supersets, so it's done before them
+    for set in 0..full {
+        if cost[set] == u128::MAX {
+            continue;
+        }
+        let pos = set.count_ones() as usize;
+        let is_placed = |a: usize| set & (1 << a) != 0;
+
+        for (x, x_degree) in degree.iter().enumerate() {
+            if is_placed(x) || !problem.constraints.allows_next(pos, None, x, is_placed) {
+                continue;
+            }
+            let w_placed: u128 = problem
+                .c
+                .neighbours(x)
+                .iter()
+                .filter(|(b, _)| is_placed(*b))
+                .map(|(_, w)| *w as u128)
+                .sum();
+
+            let next = set | (1 << x);
+            cross[next] = cross[set] + x_degree - 2 * w_placed;
+            let c = cost[set] + problem.s[x] as u128 * (x_degree + cross[set] - w_placed);
+            if c < cost[next] {
+                cost[next] = c;
+                last[next] = x as u8;
+            }
+        }
+    }
+
+    let mut solution = Vec::with_capacity(n);
+    let mut set = full;
+    while set != 0 {
+        let x = last[set] as usize;
+        solution.push(x);
+        set ^= 1 << x;
+    }
+    solution.reverse();
+
+    let fitness = problem.eval(&solution);
+
+    #[cfg(feature = "log")]
+    {
+        log::log("time", time.elapsed().as_millis());
+        log::log("evaluation", full + 1);
+        log::log("best fitness", fitness);
+        log::set_attr("algorithm", "DP");
+        log::write();
+    }
+
+    Some(ExactSolution {
+        solution,
+        fitness,
+        bound: fitness,
+    })
+}
+
+/// Depth-first branch and bound over the prefixes of the solutions, for any objective. The
+/// prefixes are pruned with `Objective::bound_prefix`, and the children of each prefix are
+/// explored best bound first.
+///
+/// The search stops when the budget of `run` is over, in which case the bound of the result is
+/// the largest bound of the prefixes left to explore.
+pub fn branch_and_bound(
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    run: &mut Run,
+) -> ExactSolution {
+    let initial = problem
+        .constraints
+        .repair(&(0..problem.n).collect::<Vec<usize>>());
+
+    let best_fitness = objective.eval(problem, &initial);
+    run.evaluated(1, best_fitness);
+
+    let mut search = Search {
+        problem,
+        objective,
+        run,
+        best_fitness,
+        best: initial,
+        prefix: Vec::with_capacity(problem.n),
+        placed: vec![false; problem.n],
+        open_bound: 0,
+        stopped: false,
+    };
+    search.branch();
+
+    #[cfg(feature = "log")]
+    {
+        log::set_attr("algorithm", "B&B");
+        log::set_attr("gap", search.open_bound.saturating_sub(search.best_fitness));
+        log::write();
+    }
+
+    ExactSolution {
+        bound: search.best_fitness.max(search.open_bound),
+        solution: search.best,
+        fitness: search.best_fitness,
+    }
+}
+
+struct Search<'a> {
+    problem: &'a CoProblem,
+    objective: &'a dyn Objective,
+    run: &'a mut Run,
+    best: Vec<usize>,
+    best_fitness: u128,
+    /// The prefix being explored and whether each item is in it
+    prefix: Vec<usize>,
+    placed: Vec<bool>,
+    /// Largest bound of the prefixes left unexplored when the search was stopped
+    open_bound: u128,
+    stopped: bool,
+}
+
+impl Search<'_> {
+    fn branch(&mut self) {
+        let n = self.problem.n;
+
+        if self.prefix.len() == n {
+            let fitness = self.objective.eval(self.problem, &self.prefix);
+            if fitness > self.best_fitness {
+                self.best_fitness = fitness;
+                self.best = self.prefix.clone();
+            }
+
+            self.run.evaluated(1, self.best_fitness);
+            return;
+        }
+
+        let pos = self.prefix.len();
+        let last = self.prefix.last().copied();
+        let mut children = vec![];
+        for x in 0..n {
+            let placed = &self.placed;
+            if placed[x]
+                || !self
+                    .problem
+                    .constraints
+                    .allows_next(pos, last, x, |a| placed[a])
+            {
+                continue;
+            }
+            self.prefix.push(x);
+            children.push((self.objective.bound_prefix(self.problem, &self.prefix), x));
+            self.prefix.pop();
+        }
+        children.sort_unstable_by(|a, b| b.cmp(a));
+
+        for (bound, x) in children {
+            // the rest of children have lower bounds
+            if bound <= self.best_fitness {
+                break;
+            }
+            if self.stopped || self.run.is_over() {
+                self.stopped = true;
+                self.open_bound = self.open_bound.max(bound);
+                continue;
+            }
+
+            self.placed[x] = true;
+            self.prefix.push(x);
+            self.branch();
+            self.prefix.pop();
+            self.placed[x] = false;
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::CoProblem;
+
+/// Number of terms written in each line of an expression of the LP format.
+const TERMS_PER_LINE: usize = 8;
+
+/// Linear expression over the variables of a `Model`, plus a constant.
+#[derive(Default)]
+struct Expr {
+    terms: Vec<(usize, i128)>,
+    constant: i128,
+}
+
+#[derive(Clone, Copy)]
+enum Sense {
+    Le,
+    Ge,
+    Eq,
+}
+
+struct Constraint {
+    name: String,
+    /// The constant of the expression is moved to the right hand side when writing it
+    expr: Expr,
+    sense: Sense,
+    rhs: i128,
+}
+
+/// A mixed-integer linear program to minimize.
+///
+/// The programs written by `lp` and `mps` are the linear ordering formulation of the interaction
+/// objective (see `CoProblem::eval`). The binary variable `x_i_j` (`i < j`) is `1` if item `i` is
+/// placed before item `j`, and `y_a_b_k` is `1` if item `k` is placed between the items `a` and
+/// `b` of an interacting pair. The distance of a pair is the size of its items plus the size of
+/// the items between them, so the program minimizes `cost = sum of w(a, b) * s[k] * y_a_b_k`,
+/// and the fitness of its solutions is a constant minus `cost` (written in the header of the
+/// files).
+struct Model {
+    /// Name of each variable and whether it is binary (otherwise it is continuous and
+    /// non-negative)
+    vars: Vec<(String, bool)>,
+    objective: Expr,
+    constraints: Vec<Constraint>,
+    /// Fitness of a solution of the problem is `offset - cost`
+    offset: u128,
+}
+
+impl Model {
+    /// Builds the linear ordering model of `problem`, including its constraints.
+    fn linear_ordering(problem: &CoProblem) -> Self {
+        let n = problem.n;
+
+        let mut vars = vec![];
+        // `x[i][j]` is the index of `x_i_j`, for `i < j`
+        let mut x = vec![vec![usize::MAX; n]; n];
+        for (i, row) in x.iter_mut().enumerate() {
+            for (j, var) in row.iter_mut().enumerate().skip(i + 1) {
+                *var = vars.len();
+                vars.push((format!("x_{i}_{j}"), true));
+            }
+        }
+        // expression that is `1` if `a` is placed before `b`
+        let before = |a: usize, b: usize| -> Expr {
+            if a < b {
+                Expr {
+                    terms: vec![(x[a][b], 1)],
+                    constant: 0,
+                }
+            } else {
+                Expr {
+                    terms: vec![(x[b][a], -1)],
+                    constant: 1,
+                }
+            }
+        };
+
+        let mut constraints = vec![];
+
+        // transitivity of the order
+        for i in 0..n {
+            for j in (i + 1)..n {
+                for k in (j + 1)..n {
+                    let expr = || Expr {
+                        terms: vec![(x[i][j], 1), (x[j][k], 1), (x[i][k], -1)],
+                        constant: 0,
+                    };
+                    constraints.push(Constraint {
+                        name: format!("t_{i}_{j}_{k}_a"),
+                        expr: expr(),
+                        sense: Sense::Le,
+                        rhs: 1,
+                    });
+                    constraints.push(Constraint {
+                        name: format!("t_{i}_{j}_{k}_b"),
+                        expr: expr(),
+                        sense: Sense::Ge,
+                        rhs: 0,
+                    });
+                }
+            }
+        }
+
+        // `y_a_b_k` is at least `1` if `k` is placed between `a` and `b` in any of the two orders
+        let s_sum: u128 = problem.s.iter().map(|s| *s as u128).sum();
+        let mut objective = Expr::default();
+        let mut offset = 0;
+        for a in 0..n {
+            for &(b, w) in problem.c.neighbours(a) {
+                if b < a {
+                    continue;
+                }
+                offset += w as u128 * (s_sum - problem.s[a] as u128 - problem.s[b] as u128);
+
+                for k in (0..n).filter(|k| *k != a && *k != b) {
+                    let y = vars.len();
+                    vars.push((format!("y_{a}_{b}_{k}"), false));
+                    objective.terms.push((y, w as i128 * problem.s[k] as i128));
+
+                    for (side, (first, last)) in [("a", (a, b)), ("b", (b, a))] {
+                        let expr = before(first, k)
+                            .add(&before(k, last), 1)
+                            .add(&Expr::var(y), -1);
+                        constraints.push(Constraint {
+                            name: format!("y_{a}_{b}_{k}_{side}"),
+                            expr,
+                            sense: Sense::Le,
+                            rhs: 1,
+                        });
+                    }
+                }
+            }
+        }
+
+        let lists = &problem.constraints;
+        for (a, b) in lists.precedence() {
+            constraints.push(Constraint {
+                name: format!("p_{a}_{b}"),
+                expr: before(*a, *b),
+                sense: Sense::Eq,
+                rhs: 1,
+            });
+        }
+        // the position of an item is the number of items placed before it
+        for (item, pos) in lists.pinned() {
+            let expr = (0..n)
+                .filter(|k| k != item)
+                .fold(Expr::default(), |e, k| e.add(&before(k, *item), 1));
+            constraints.push(Constraint {
+                name: format!("pin_{item}"),
+                expr,
+                sense: Sense::Eq,
+                rhs: *pos as i128,
+            });
+        }
+        // `b` right after `a`: every other item is placed either before both or after both
+        for (a, b) in lists.adjacent() {
+            constraints.push(Constraint {
+                name: format!("adj_{a}_{b}"),
+                expr: before(*a, *b),
+                sense: Sense::Eq,
+                rhs: 1,
+            });
+            for k in (0..n).filter(|k| k != a && k != b) {
+                constraints.push(Constraint {
+                    name: format!("adj_{a}_{b}_{k}"),
+                    expr: before(k, *a).add(&before(k, *b), -1),
+                    sense: Sense::Eq,
+                    rhs: 0,
+                });
+            }
+        }
+
+        Model {
+            vars,
+            objective,
+            constraints,
+            offset,
+        }
+    }
+}
+
+impl Expr {
+    fn var(v: usize) -> Self {
+        Expr {
+            terms: vec![(v, 1)],
+            constant: 0,
+        }
+    }
+
+    /// Returns `self + factor * other`.
+    fn add(mut self, other: &Expr, factor: i128) -> Self {
+        self.terms
+            .extend(other.terms.iter().map(|(v, c)| (*v, c * factor)));
+        self.constant += other.constant * factor;
+        self
+    }
+}
+
+/// Writes `problem` as a mixed-integer program in CPLEX LP format. `name` is only written in the
+/// header comment.
+pub fn lp(problem: &CoProblem, name: &str) -> String {
+    let model = Model::linear_ordering(problem);
+    let mut out = String::new();
+
+    writeln!(out, "\\ Layout of `{name}`, {} items", problem.n).unwrap();
+    writeln!(out, "\\ fitness = {} - cost", model.offset).unwrap();
+    writeln!(out, "Minimize").unwrap();
+    write!(out, " cost:").unwrap();
+    write_terms(&mut out, &model, &model.objective.terms);
+    writeln!(out).unwrap();
+
+    writeln!(out, "Subject To").unwrap();
+    for c in model.constraints.iter() {
+        write!(out, " {}:", c.name).unwrap();
+        write_terms(&mut out, &model, &c.expr.terms);
+        let sense = match c.sense {
+            Sense::Le => "<=",
+            Sense::Ge => ">=",
+            Sense::Eq => "=",
+        };
+        writeln!(out, " {sense} {}", c.rhs - c.expr.constant).unwrap();
+    }
+
+    writeln!(out, "Binary").unwrap();
+    for (var, _) in model.vars.iter().filter(|(_, binary)| *binary) {
+        writeln!(out, " {var}").unwrap();
+    }
+    writeln!(out, "End").unwrap();
+
+    out
+}
+
+/// Writes the terms of an LP expression, breaking the lines every few terms.
+fn write_terms(out: &mut String, model: &Model, terms: &[(usize, i128)]) {
+    if terms.is_empty() {
+        write!(out, " 0").unwrap();
+    }
+    for (k, (var, coef)) in terms.iter().enumerate() {
+        if k > 0 && k % TERMS_PER_LINE == 0 {
+            write!(out, "\n  ").unwrap();
+        }
+        let sign = if *coef < 0 { '-' } else { '+' };
+        write!(out, " {sign} {} {}", coef.abs(), model.vars[*var].0).unwrap();
+    }
+}
+
+/// Writes `problem` as a mixed-integer program in free MPS format, with the same model as `lp`.
+pub fn mps(problem: &CoProblem, name: &str) -> String {
+    let model = Model::linear_ordering(problem);
+    let mut out = String::new();
+
+    // the coefficients of each variable, by column
+    let mut columns = vec![vec![]; model.vars.len()];
+    for (var, coef) in model.objective.terms.iter() {
+        columns[*var].push(("cost", *coef));
+    }
+    for c in model.constraints.iter() {
+        for (var, coef) in c.expr.terms.iter() {
+            columns[*var].push((c.name.as_str(), *coef));
+        }
+    }
+
+    writeln!(out, "* Layout of `{name}`, {} items", problem.n).unwrap();
+    writeln!(out, "* fitness = {} - cost", model.offset).unwrap();
+    writeln!(out, "NAME pgo_co").unwrap();
+    writeln!(out, "ROWS").unwrap();
+    writeln!(out, " N cost").unwrap();
+    for c in model.constraints.iter() {
+        let sense = match c.sense {
+            Sense::Le => 'L',
+            Sense::Ge => 'G',
+            Sense::Eq => 'E',
+        };
+        writeln!(out, " {sense} {}", c.name).unwrap();
+    }
+
+    writeln!(out, "COLUMNS").unwrap();
+    for ((var, _), coefs) in model.vars.iter().zip(columns.iter()) {
+        // rows with repeated variables are summed by the readers
+        for (row, coef) in coefs {
+            writeln!(out, " {var} {row} {coef}").unwrap();
+        }
+    }
+
+    writeln!(out, "RHS").unwrap();
+    for c in model.constraints.iter() {
+        let rhs = c.rhs - c.expr.constant;
+        if rhs != 0 {
+            writeln!(out, " RHS {} {rhs}", c.name).unwrap();
+        }
+    }
+
+    writeln!(out, "BOUNDS").unwrap();
+    for (var, _) in model.vars.iter().filter(|(_, binary)| *binary) {
+        writeln!(out, " BV BND {var}").unwrap();
+    }
+    writeln!(out, "ENDATA").unwrap();
+
+    out
+}
+
+/// Writes `problem` as a QAPLIB instance: the size `n`, the flow matrix (the interactions) and
+/// the distance matrix of the positions. A solution of cost `cost` has fitness
+/// `(n * size) * (summed interaction) - cost`.
+///
+/// The distance between two positions only depends on the positions if all the items have the
+/// same size, and QAPLIB has no constraints, so it returns `None` otherwise.
+pub fn qaplib(problem: &CoProblem) -> Option<String> {
+    let n = problem.n;
+    let size = *problem.s.first()?;
+    if problem.s.iter().any(|s| *s != size) || !problem.constraints.is_empty() {
+        return None;
+    }
+
+    let mut out = String::new();
+    writeln!(out, "{n}\n").unwrap();
+    for a in 0..n {
+        let row = (0..n)
+            .map(|b| if a == b { 0 } else { problem.c.get(a, b) })
+            .map(|w| w.to_string())
+            .collect::<Vec<String>>();
+        writeln!(out, "{}", row.join(" ")).unwrap();
+    }
+    writeln!(out).unwrap();
+    // the distance between two items is their size plus the size of the items between them
+    for p in 0..n {
+        let row = (0..n)
+            .map(|q| {
+                if p == q {
+                    0
+                } else {
+                    (p.max(q) - p.min(q) + 1) * size
+                }
+            })
+            .map(|d| d.to_string())
+            .collect::<Vec<String>>();
+        writeln!(out, "{}", row.join(" ")).unwrap();
+    }
+
+    Some(out)
+}
+
+/// Reads the solution of the model written by `lp` or `mps` from a solver's solution file, and
+/// returns it as a permutation of the items of `problem`.
+///
+/// Only the `x_i_j` variables are read, and the missing ones are taken as `0`. Files that list
+/// each variable in a line followed by its value (as the ones written by Gurobi, SCIP, HiGHS or
+/// CBC) and CPLEX's XML solutions are supported.
+pub fn read_lp_solution(problem: &CoProblem, text: &str) -> Result<Vec<usize>, String> {
+    let n = problem.n;
+    let mut values = HashMap::new();
+
+    for line in text.lines() {
+        if let (Some(name), Some(value)) = (xml_attr(line, "name"), xml_attr(line, "value")) {
+            values.insert(name, value);
+            continue;
+        }
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+        if let Some(k) = tokens.iter().position(|t| t.starts_with("x_")) {
+            if let Some(value) = tokens[k + 1..].iter().find(|t| t.parse::<f64>().is_ok()) {
+                values.insert(tokens[k], value);
+            }
+        }
+    }
+
+    let is_before = |i: usize, j: usize| -> Result<bool, String> {
+        let (lo, hi) = (i.min(j), i.max(j));
+        let value = match values.get(format!("x_{lo}_{hi}").as_str()) {
+            Some(v) => v
+                .parse::<f64>()
+                .map_err(|e| format!("invalid value of x_{lo}_{hi}: {e}"))?,
+            None => 0.0,
+        };
+        Ok((value > 0.5) == (i < j))
+    };
+
+    // the position of each item is the number of items placed before it
+    let mut solution = vec![usize::MAX; n];
+    for item in 0..n {
+        let mut pos = 0;
+        for k in (0..n).filter(|k| *k != item) {
+            pos += is_before(k, item)? as usize;
+        }
+        if solution[pos] != usize::MAX {
+            return Err("the solution is not a linear order".to_string());
+        }
+        solution[pos] = item;
+    }
+
+    Ok(solution)
+}
+
+/// Reads a QAPLIB solution (`n`, the cost and the location of each facility, numbered from `1`)
+/// of the instance written by `qaplib`, and returns it as a permutation of the items of
+/// `problem`.
+pub fn read_qaplib_solution(problem: &CoProblem, text: &str) -> Result<Vec<usize>, String> {
+    let n = problem.n;
+    let numbers = text
+        .split_whitespace()
+        .map(|t| t.parse::<u128>().map_err(|e| format!("{t}: {e}")))
+        .collect::<Result<Vec<u128>, String>>()?;
+
+    match numbers.first() {
+        Some(size) if *size == n as u128 && numbers.len() == n + 2 => (),
+        _ => return Err(format!("expected a solution of {n} items")),
+    }
+
+    let mut solution = vec![usize::MAX; n];
+    for (item, location) in numbers[2..].iter().enumerate() {
+        let pos = (*location as usize).wrapping_sub(1);
+        if pos >= n || solution[pos] != usize::MAX {
+            return Err("the solution is not a permutation".to_string());
+        }
+        solution[pos] = item;
+    }
+
+    Ok(solution)
+}
+
+/// Returns the value of the `attr="value"` attribute of an XML line.
+fn xml_attr<'a>(line: &'a str, attr: &str) -> Option<&'a str> {
+    let start = line.find(&format!(" {attr}=\""))? + attr.len() + 3;
+    let len = line[start..].find('"')?;
+    Some(&line[start..start + len])
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use super::{CoProblem, Constraints, Interactions};
+use crate::profdata::Module;
+
+/// Inter-procedural layout problem: the items are the functions defined in a module, their
+/// sizes the size of their code, and the interaction between two functions the number of times
+/// one calls the other.
+#[derive(Serialize, Deserialize)]
+pub struct FunctionOrdering {
+    /// Name of the function of each item
+    pub functions: Vec<String>,
+    #[serde(flatten)]
+    pub problem: CoProblem,
+}
+
+impl FunctionOrdering {
+    /// Builds the problem from the call graph of `module`.
+    ///
+    /// The number of times a call site is executed is its own count if the profile records it,
+    /// and the execution count of its basic block otherwise (inferred from the function's entry
+    /// count and branch weights, see `Function::infer_branch_weights`). Indirect calls are not
+    /// taken into account.
+    ///
+    /// Returns `None` if no call has been executed.
+    pub fn from_module(module: &Module) -> Option<Self> {
+        let index: HashMap<&str, usize> = module
+            .functions
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.as_str(), i))
+            .collect();
+
+        let mut edges = vec![];
+        for (caller, function) in module.functions.iter().enumerate() {
+            if function.call_sites.is_empty() {
+                continue;
+            }
+            let block_counts = function.infer_branch_weights().block_counts;
+
+            for call_site in function.call_sites.iter() {
+                // calls to external functions are not part of the problem
+                if let Some(callee) = index.get(call_site.callee.as_str()) {
+                    let count = call_site.count.unwrap_or(block_counts[call_site.bb]);
+                    edges.push((caller, *callee, count));
+                }
+            }
+        }
+
+        let n = module.functions.len();
+        let c = Interactions::from_edges(n, edges);
+        if c.num_edges() == 0 {
+            return None;
+        }
+
+        let s = module
+            .functions
+            .iter()
+            .map(|f| f.bbs_sizes().iter().sum())
+            .collect();
+
+        Some(FunctionOrdering {
+            functions: module.functions.iter().map(|f| f.name.clone()).collect(),
+            problem: CoProblem {
+                c,
+                s,
+                n,
+                constraints: Constraints::default(),
+            },
+        })
+    }
+
+    /// Returns the names of the functions in the order given by `solution`.
+    pub fn function_order(&self, solution: &[usize]) -> Vec<&str> {
+        solution
+            .iter()
+            .map(|i| self.functions[*i].as_str())
+            .collect()
+    }
+}
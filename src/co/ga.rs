@@ -0,0 +1,202 @@
+use rand::prelude::*;
+
+use std::cmp::Reverse;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::crossover::Crossover;
+use super::neighborhood::Neighborhood;
+use super::parallel::{Evolutionary, Individual};
+use super::solver::{Run, Solver};
+use super::{CoProblem, Objective};
+
+/// Genetic algorithm over permutations with tournament selection. The generational version
+/// replaces the whole population each generation but for its `elitism` best solutions, and the
+/// steady-state one replaces the worst solution with each child that is at least as good.
+pub struct Ga {
+    pub pop_size: usize,
+    pub crossover: Crossover,
+    /// Probability of crossing the parents, instead of copying the first one
+    pub crossover_prob: f64,
+    /// Neighborhood the random move of the mutation is drawn from
+    pub mutation: Neighborhood,
+    pub mutation_prob: f64,
+    /// Number of solutions, drawn with replacement, that compete in each tournament
+    pub tournament_size: usize,
+    /// Number of the best solutions kept from one generation to the next (generational only)
+    pub elitism: usize,
+    pub steady_state: bool,
+}
+
+impl Default for Ga {
+    fn default() -> Self {
+        Ga {
+            pop_size: 100,
+            crossover: Crossover::Order,
+            crossover_prob: 0.9,
+            mutation: Neighborhood::Insert,
+            mutation_prob: 0.2,
+            tournament_size: 3,
+            elitism: 2,
+            steady_state: false,
+        }
+    }
+}
+
+impl Ga {
+    /// Index of the winner of a tournament among the solutions of `pop`.
+    fn tournament(&self, pop: &[Individual], rng: &mut impl Rng) -> usize {
+        (0..self.tournament_size.max(1))
+            .map(|_| rng.gen_range(0..pop.len()))
+            .max_by_key(|i| pop[*i].1)
+            .unwrap()
+    }
+
+    /// A new solution, bred from two parents of `pop`, and its fitness.
+    fn offspring(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        pop: &[Individual],
+        rng: &mut impl Rng,
+    ) -> Individual {
+        let p1 = self.tournament(pop, rng);
+        let p2 = self.tournament(pop, rng);
+
+        let mut child = if rng.gen::<f64>() < self.crossover_prob {
+            self.crossover.apply(&pop[p1].0, &pop[p2].0, rng)
+        } else {
+            pop[p1].0.clone()
+        };
+        if rng.gen::<f64>() < self.mutation_prob {
+            child = self.mutation.random_move(child.len(), rng).neighbor(&child);
+        }
+
+        let child = problem.constraints.repair(&child);
+        let f = objective.eval(problem, &child);
+        (child, f)
+    }
+}
+
+impl Solver for Ga {
+    fn name(&self) -> &'static str {
+        "GA"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut pop = self.init(problem, objective, run);
+        while problem.n > 1 && !run.is_over() {
+            self.generation(problem, objective, &mut pop, run);
+        }
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("pop size", self.pop_size);
+            log::set_attr("crossover", format!("{:?}", self.crossover));
+            log::set_attr("crossover prob", self.crossover_prob);
+            log::set_attr("mutation", format!("{:?}", self.mutation));
+            log::set_attr("mutation prob", self.mutation_prob);
+            log::set_attr("tournament size", self.tournament_size);
+            log::set_attr("elitism", self.elitism);
+            log::set_attr("steady state", self.steady_state);
+        }
+
+        pop.best
+    }
+}
+
+/// Population of a `Ga`, and the best solution it has found.
+pub struct GaPopulation {
+    pop: Vec<Individual>,
+    best: Individual,
+}
+
+impl Evolutionary for Ga {
+    type Population = GaPopulation;
+
+    fn init(&self, problem: &CoProblem, objective: &dyn Objective, run: &mut Run) -> GaPopulation {
+        let mut pop: Vec<Individual> = Vec::with_capacity(self.pop_size);
+        let mut best: Individual = (vec![], 0);
+
+        for _ in 0..self.pop_size.max(1) {
+            let mut s = (0..problem.n).collect::<Vec<usize>>();
+            s.shuffle(&mut run.rng);
+            let s = problem.constraints.repair(&s);
+            let f = objective.eval(problem, &s);
+
+            if best.0.is_empty() || f > best.1 {
+                best = (s.clone(), f);
+            }
+            run.evaluated(1, best.1);
+            pop.push((s, f));
+
+            if run.is_over() {
+                break;
+            }
+        }
+
+        GaPopulation { pop, best }
+    }
+
+    /// A single child in the steady-state version.
+    fn generation(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        pop: &mut GaPopulation,
+        run: &mut Run,
+    ) {
+        let GaPopulation { pop, best } = pop;
+
+        if self.steady_state {
+            let child = self.offspring(problem, objective, pop, &mut run.rng);
+            if child.1 > best.1 {
+                *best = child.clone();
+            }
+            run.evaluated(1, best.1);
+
+            let (worst, _) = pop.iter().enumerate().min_by_key(|(_, s)| s.1).unwrap();
+            if child.1 >= pop[worst].1 {
+                pop[worst] = child;
+            }
+        } else {
+            // the best solutions first
+            pop.sort_by_key(|s| Reverse(s.1));
+
+            let mut next = pop[..self.elitism.min(pop.len())].to_vec();
+            while next.len() < pop.len() && !run.is_over() {
+                let child = self.offspring(problem, objective, pop, &mut run.rng);
+                if child.1 > best.1 {
+                    *best = child.clone();
+                }
+                run.evaluated(1, best.1);
+                next.push(child);
+            }
+            *pop = next;
+        }
+    }
+
+    fn best(&self, pop: &GaPopulation) -> Individual {
+        pop.best.clone()
+    }
+
+    fn emigrants(&self, pop: &GaPopulation, count: usize) -> Vec<Individual> {
+        let mut sorted = pop.pop.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|s| Reverse(s.1));
+        sorted.into_iter().take(count).cloned().collect()
+    }
+
+    fn immigrate(&self, pop: &mut GaPopulation, migrants: Vec<Individual>) {
+        // the worst solutions last
+        pop.pop.sort_by_key(|s| Reverse(s.1));
+        let count = migrants.len().min(pop.pop.len());
+        pop.pop.truncate(pop.pop.len() - count);
+        pop.pop.extend(migrants.into_iter().take(count));
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::constructive::{construct_solution, Constructive};
+use super::local_search::climb;
+use super::solver::{Run, Solver};
+use super::{CoProblem, Layout, Objective};
+
+/// Greedy randomized adaptive search procedure: solutions built by the constructive heuristic,
+/// with a restricted candidate list, are improved by hill climbing over the swap neighborhood
+/// until the budget is over.
+pub struct Grasp {
+    /// Exponent of the weights of the items when picking the next one
+    pub tau1: u32,
+    /// Exponent of the weights of the successors of the picked item
+    pub tau2: u32,
+    /// Greediness of the construction, from 0 (only the heaviest candidates) to 1 (all of them,
+    /// as the constructive solver)
+    pub alpha: f64,
+}
+
+impl Default for Grasp {
+    fn default() -> Self {
+        let constructive = Constructive::default();
+        Grasp {
+            tau1: constructive.tau1,
+            tau2: constructive.tau2,
+            alpha: 0.5,
+        }
+    }
+}
+
+impl Solver for Grasp {
+    fn name(&self) -> &'static str {
+        "GRASP"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut best: Option<Layout> = None;
+
+        loop {
+            let best_f = best.as_ref().map_or(0, |b| b.fitness());
+
+            let s = construct_solution(problem, self.tau1, self.tau2, self.alpha, &mut run.rng);
+            let mut layout = Layout::new(problem, objective, s);
+            run.evaluated(1, best_f.max(layout.fitness()));
+            climb(problem, objective, &mut layout, best_f, run);
+
+            if best.is_none() || layout.fitness() > best_f {
+                best = Some(layout);
+            }
+
+            if run.is_over() {
+                break;
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("tau1", self.tau1);
+            log::set_attr("tau2", self.tau2);
+            log::set_attr("alpha", self.alpha);
+        }
+
+        let best = best.unwrap();
+        let best_f = best.fitness();
+        (best.into_order(), best_f)
+    }
+}
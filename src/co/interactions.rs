@@ -0,0 +1,190 @@
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+/// Sparse interaction matrix of a `CoProblem`, stored in compressed sparse row (CSR) format.
+///
+/// Besides the directed interactions `c[a][b]`, the symmetric pair weights
+/// `c[a][b] + c[b][a]` are also kept, as they are what the objective function works with.
+#[derive(Clone, Debug)]
+pub struct Interactions {
+    n: usize,
+    // `out[out_offsets[a]..out_offsets[a + 1]]` are the nonzero `(b, c[a][b])` of row `a`
+    out_offsets: Vec<usize>,
+    out: Vec<(usize, u64)>,
+    // same layout as `out`, but with the `(b, c[a][b] + c[b][a])` pairs, for `a != b`
+    pair_offsets: Vec<usize>,
+    pairs: Vec<(usize, u64)>,
+}
+
+impl Interactions {
+    /// Builds the matrix from a list of `(from, to, weight)` entries. Repeated entries are
+    /// summed up and zero weights are dropped.
+    pub fn from_edges(n: usize, edges: impl IntoIterator<Item = (usize, usize, u64)>) -> Self {
+        let mut rows = vec![vec![]; n];
+        let mut sym_rows = vec![vec![]; n];
+
+        for (a, b, w) in edges {
+            if w == 0 {
+                continue;
+            }
+            rows[a].push((b, w));
+            if a != b {
+                sym_rows[a].push((b, w));
+                sym_rows[b].push((a, w));
+            }
+        }
+
+        let (out_offsets, out) = Self::compress(rows);
+        let (pair_offsets, pairs) = Self::compress(sym_rows);
+
+        Interactions {
+            n,
+            out_offsets,
+            out,
+            pair_offsets,
+            pairs,
+        }
+    }
+
+    pub fn from_dense(c: &[Vec<u64>]) -> Self {
+        let edges = c
+            .iter()
+            .enumerate()
+            .flat_map(|(a, row)| row.iter().enumerate().map(move |(b, w)| (a, b, *w)));
+        Self::from_edges(c.len(), edges)
+    }
+
+    /// Sorts each row by column, merges repeated columns and flattens the rows.
+    fn compress(rows: Vec<Vec<(usize, u64)>>) -> (Vec<usize>, Vec<(usize, u64)>) {
+        let mut offsets = Vec::with_capacity(rows.len() + 1);
+        let mut flat: Vec<(usize, u64)> = vec![];
+        offsets.push(0);
+
+        for mut row in rows {
+            row.sort_by_key(|(b, _)| *b);
+            let start = flat.len();
+            for (b, w) in row {
+                if flat.len() > start && flat[flat.len() - 1].0 == b {
+                    flat.last_mut().unwrap().1 += w;
+                } else {
+                    flat.push((b, w));
+                }
+            }
+            offsets.push(flat.len());
+        }
+
+        (offsets, flat)
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Returns `c[a][b]`.
+    pub fn get(&self, a: usize, b: usize) -> u64 {
+        let row = self.successors(a);
+        match row.binary_search_by_key(&b, |(j, _)| *j) {
+            Ok(idx) => row[idx].1,
+            Err(_) => 0,
+        }
+    }
+
+    /// Returns the nonzero `(b, c[a][b])` entries of row `a`, sorted by `b`.
+    pub fn successors(&self, a: usize) -> &[(usize, u64)] {
+        &self.out[self.out_offsets[a]..self.out_offsets[a + 1]]
+    }
+
+    /// Returns the `(b, c[a][b] + c[b][a])` entries of all the items `b != a` that interact
+    /// with `a`, sorted by `b`.
+    pub fn neighbours(&self, a: usize) -> &[(usize, u64)] {
+        &self.pairs[self.pair_offsets[a]..self.pair_offsets[a + 1]]
+    }
+
+    /// Sum of the `a`-th row.
+    pub fn row_sum(&self, a: usize) -> u64 {
+        self.successors(a).iter().map(|(_, w)| w).sum()
+    }
+
+    /// Iterates over all the nonzero `(from, to, weight)` entries.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, u64)> + '_ {
+        (0..self.n).flat_map(move |a| self.successors(a).iter().map(move |(b, w)| (a, *b, *w)))
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.out.len()
+    }
+}
+
+impl Serialize for Interactions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.num_edges()))?;
+        for edge in self.edges() {
+            seq.serialize_element(&edge)?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn matches_dense_matrix() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for n in [0, 1, 2, 7] {
+            // repeated entries, zeros and self interactions included
+            let edges = (0..4 * n)
+                .map(|_| {
+                    (
+                        rng.gen_range(0..n),
+                        rng.gen_range(0..n),
+                        rng.gen_range(0..3),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let mut dense = vec![vec![0; n]; n];
+            for &(a, b, w) in &edges {
+                dense[a][b] += w;
+            }
+            let c = Interactions::from_edges(n, edges);
+
+            for (a, row) in dense.iter().enumerate() {
+                for (b, w) in row.iter().enumerate() {
+                    assert_eq!(c.get(a, b), *w);
+                }
+                let successors = row
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, w)| **w > 0)
+                    .map(|(b, w)| (b, *w))
+                    .collect::<Vec<_>>();
+                assert_eq!(c.successors(a), successors);
+                let neighbours = (0..n)
+                    .map(|b| (b, dense[a][b] + dense[b][a]))
+                    .filter(|&(b, w)| b != a && w > 0)
+                    .collect::<Vec<_>>();
+                assert_eq!(c.neighbours(a), neighbours);
+                assert_eq!(c.row_sum(a), row.iter().sum());
+            }
+
+            // both the entries and the dense matrix build the same CSR back
+            for other in [
+                Interactions::from_edges(n, c.edges()),
+                Interactions::from_dense(&dense),
+            ] {
+                assert_eq!(other.len(), n);
+                assert_eq!(
+                    other.edges().collect::<Vec<_>>(),
+                    c.edges().collect::<Vec<_>>()
+                );
+                assert_eq!(other.num_edges(), c.num_edges());
+            }
+        }
+    }
+}
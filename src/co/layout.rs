@@ -0,0 +1,305 @@
+use super::{CoProblem, Objective};
+
+/// A solution of a `CoProblem` together with its fitness and the data that the deltas of
+/// `CoProblem` (`delta_swap`, `delta_insert`, ...) need to evaluate moves incrementally.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    /// The permutation: `order[k]` is the item placed at position `k`
+    pub(super) order: Vec<usize>,
+    /// Inverse of `order`: `pos[item]` is the position of `item`
+    pub(super) pos: Vec<usize>,
+    /// `prefix[k]` is the sum of the sizes of the items placed at positions `0..k`
+    pub(super) prefix: Vec<u64>,
+    /// `cut[k]` is the summed interaction of all the item pairs whose span contains position `k`
+    pub(super) cut: Vec<u128>,
+    /// Summed interaction of all the item pairs
+    weight_sum: u128,
+    /// Fitness of `order` under the objective the layout was built with
+    pub(super) fitness: u128,
+}
+
+impl Layout {
+    pub fn new(problem: &CoProblem, objective: &dyn Objective, order: Vec<usize>) -> Self {
+        let mut layout = Layout {
+            pos: vec![0; order.len()],
+            prefix: vec![0; order.len() + 1],
+            cut: vec![0; order.len()],
+            weight_sum: 0,
+            fitness: 0,
+            order,
+        };
+        layout.refresh(problem, objective);
+        layout
+    }
+
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    pub fn fitness(&self) -> u128 {
+        self.fitness
+    }
+
+    pub fn into_order(self) -> Vec<usize> {
+        self.order
+    }
+
+    /// Swaps the items at positions `i` and `j`.
+    pub fn swap(&mut self, problem: &CoProblem, objective: &dyn Objective, i: usize, j: usize) {
+        if i == j {
+            return;
+        }
+        let (i, j) = min_max(i, j);
+        let delta = objective.delta_swap(problem, self, i, j);
+        let (x, y) = (self.order[i], self.order[j]);
+
+        // only the pairs of `x` and `y` change their span, and only inside `i..=j`
+        let mut diff = Diff::new(i, j);
+        for (item, old, new, other) in [(x, i, j, y), (y, j, i, x)] {
+            for &(r, w) in problem.c.neighbours(item) {
+                if r == other {
+                    continue;
+                }
+                let pr = self.pos[r];
+                let (lo, hi) = min_max(old, pr);
+                diff.add(lo, hi + 1, -(w as i128));
+                let (lo, hi) = min_max(new, pr);
+                diff.add(lo, hi + 1, w as i128);
+            }
+        }
+
+        self.order.swap(i, j);
+        for (k, change) in (i..=j).zip(diff.changes()) {
+            self.cut[k] = (self.cut[k] as i128 + change) as u128;
+        }
+        self.update(problem, i, j, delta);
+    }
+
+    /// Removes the item at position `from` and inserts it back so that it ends up at position `to`.
+    pub fn insert(
+        &mut self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        from: usize,
+        to: usize,
+    ) {
+        // the same as moving a segment of a single item
+        if from != to {
+            let delta = objective.delta_insert(problem, self, from, to);
+            self.swap_blocks(problem, from, 1, to, delta);
+        }
+    }
+
+    /// Moves the `len` items starting at position `start` so that they start at position `to`.
+    pub fn move_segment(
+        &mut self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        start: usize,
+        len: usize,
+        to: usize,
+    ) {
+        if start != to && len > 0 {
+            let delta = objective.delta_move_segment(problem, self, start, len, to);
+            self.swap_blocks(problem, start, len, to, delta);
+        }
+    }
+
+    /// Reverses the items at positions `i..=j`.
+    pub fn reverse(&mut self, problem: &CoProblem, objective: &dyn Objective, i: usize, j: usize) {
+        let (i, j) = min_max(i, j);
+        if i == j {
+            return;
+        }
+        let delta = objective.delta_reverse(problem, self, i, j);
+        let mirror = |p: usize| i + j - p;
+
+        // the pairs inside or around the segment span the mirrored positions, but the ones with a
+        // single item in it keep their other end: the change to the mirrored cut is the span of
+        // the new pair minus the mirrored span of the old one
+        let mut diff = Diff::new(i, j);
+        for p in i..=j {
+            for &(r, w) in problem.c.neighbours(self.order[p]) {
+                let (w, pr) = (w as i128, self.pos[r]);
+                if pr < i {
+                    diff.add(i, mirror(p) + 1, w);
+                    diff.add(mirror(p), j + 1, -w);
+                } else if pr > j {
+                    diff.add(mirror(p), j + 1, w);
+                    diff.add(i, mirror(p) + 1, -w);
+                }
+            }
+        }
+
+        self.order[i..=j].reverse();
+        self.cut[i..=j].reverse();
+        for (k, change) in (i..=j).zip(diff.changes()) {
+            self.cut[k] = (self.cut[k] as i128 + change) as u128;
+        }
+        self.update(problem, i, j, delta);
+    }
+
+    /// Moves the `len` items starting at `start` so that they start at `to`, which swaps them
+    /// with the block of items they jump over, given the change in fitness it produces.
+    fn swap_blocks(
+        &mut self,
+        problem: &CoProblem,
+        start: usize,
+        len: usize,
+        to: usize,
+        delta: i128,
+    ) {
+        // `lo..mid` is the block on the left and `mid..hi` the one on the right
+        let (lo, mid, hi) = if start < to {
+            (start, start + len, to + len)
+        } else {
+            (to, start, start + len)
+        };
+        let segment_is_left = start < to;
+
+        // the pairs between the blocks only change how they span the items of the blocks. Those
+        // of the segment are read from its items, and the rest from the cut around the blocks
+        let mut diff = Diff::new(lo, hi - 1);
+        let (mut w_before, mut w_after) = (0, 0);
+        for p in start..start + len {
+            for &(r, w) in problem.c.neighbours(self.order[p]) {
+                let (w, pr) = (w as i128, self.pos[r]);
+                let (x, y) = if segment_is_left { (p, pr) } else { (pr, p) };
+                if pr < lo {
+                    w_before += w;
+                } else if pr >= hi {
+                    w_after += w;
+                } else if (mid..hi).contains(&y) && (lo..mid).contains(&x) {
+                    // the items of the right block after `y` start being spanned and the ones
+                    // before it stop, and the other way around for the items of the left block
+                    diff.add(y + 1, hi, w);
+                    diff.add(mid, y, -w);
+                    diff.add(lo, x, w);
+                    diff.add(x + 1, mid, -w);
+                }
+            }
+        }
+
+        // the pairs between a block and the items outside of both blocks start spanning the
+        // other block if they didn't, or the other way around
+        let cross_lo = problem.cross(self, lo);
+        let cross_hi = problem.cross(self, hi);
+        let (left_change, right_change) = if segment_is_left {
+            (cross_hi - w_after - cross_lo + w_before, w_before - w_after)
+        } else {
+            (w_after - w_before, cross_lo - w_before - cross_hi + w_after)
+        };
+        diff.add(lo, mid, left_change);
+        diff.add(mid, hi, right_change);
+
+        let cut = (lo..hi)
+            .zip(diff.changes())
+            .map(|(k, change)| (self.cut[k] as i128 + change) as u128)
+            .collect::<Vec<u128>>();
+        let right_len = hi - mid;
+        self.order[lo..hi].rotate_left(mid - lo);
+        self.cut[lo..lo + right_len].copy_from_slice(&cut[mid - lo..]);
+        self.cut[lo + right_len..hi].copy_from_slice(&cut[..mid - lo]);
+        self.update(problem, lo, hi - 1, delta);
+    }
+
+    /// Updates the positions and the prefix sums of the items at positions `lo..=hi`, the only
+    /// ones a move changed, and the fitness with the `delta` of the move.
+    fn update(&mut self, problem: &CoProblem, lo: usize, hi: usize, delta: i128) {
+        for k in lo..=hi {
+            let item = self.order[k];
+            self.pos[item] = k;
+            self.prefix[k + 1] = self.prefix[k] + problem.s[item] as u64;
+        }
+        self.fitness = (self.fitness as i128 + delta) as u128;
+    }
+
+    /// Fitness of the layout under the interaction objective (see `CoProblem::eval`), computed
+    /// from the cached cut in linear time.
+    pub(super) fn interaction_fitness(&self, problem: &CoProblem) -> u128 {
+        let weighted_cut: u128 = self
+            .cut
+            .iter()
+            .zip(self.order.iter())
+            .map(|(cut, item)| *cut * problem.s[*item] as u128)
+            .sum();
+
+        // sum over pairs of `w * (s_sum - distance)` rewritten in terms of the cut
+        self.prefix[self.order.len()] as u128 * self.weight_sum - weighted_cut
+    }
+
+    /// Recomputes all the cached data and the fitness from `order`.
+    fn refresh(&mut self, problem: &CoProblem, objective: &dyn Objective) {
+        let n = self.order.len();
+
+        for (k, item) in self.order.iter().enumerate() {
+            self.pos[*item] = k;
+            self.prefix[k + 1] = self.prefix[k] + problem.s[*item] as u64;
+        }
+
+        // difference array of the cut: each pair adds its weight to all the positions it spans
+        let mut diff = vec![0i128; n + 1];
+        let mut total_w: u128 = 0;
+        for a in 0..n {
+            for &(b, w) in problem.c.neighbours(a) {
+                if b < a {
+                    continue;
+                }
+                let (lo, hi) = min_max(self.pos[a], self.pos[b]);
+                diff[lo] += w as i128;
+                diff[hi + 1] -= w as i128;
+                total_w += w as u128;
+            }
+        }
+
+        let mut acc = 0;
+        for (k, d) in diff.iter().take(n).enumerate() {
+            acc += d;
+            self.cut[k] = acc as u128;
+        }
+        self.weight_sum = total_w;
+
+        self.fitness = objective.eval_layout(problem, self);
+    }
+}
+
+pub(super) fn min_max(a: usize, b: usize) -> (usize, usize) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Difference array of the changes in the cut of the positions `lo..=hi`.
+struct Diff {
+    lo: usize,
+    diff: Vec<i128>,
+}
+
+impl Diff {
+    fn new(lo: usize, hi: usize) -> Self {
+        Diff {
+            lo,
+            diff: vec![0; hi - lo + 2],
+        }
+    }
+
+    /// Adds `w` to the positions `a..b` that are in the range, if any.
+    fn add(&mut self, a: usize, b: usize, w: i128) {
+        let end = self.lo + self.diff.len() - 1;
+        let (a, b) = (a.max(self.lo), b.min(end));
+        if a < b {
+            self.diff[a - self.lo] += w;
+            self.diff[b - self.lo] -= w;
+        }
+    }
+
+    /// The change of each position of the range.
+    fn changes(&self) -> impl Iterator<Item = i128> + '_ {
+        self.diff[..self.diff.len() - 1].iter().scan(0, |acc, d| {
+            *acc += d;
+            Some(*acc)
+        })
+    }
+}
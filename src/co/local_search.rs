@@ -1,80 +1,83 @@
 use rand::seq::SliceRandom;
 
-#[cfg(feature = "log")]
-use crate::log;
+use super::solver::{Run, Solver};
+use super::{CoProblem, Layout, Objective};
 
-use std::time::Instant;
+/// Best-first hill climbing over the swap neighborhood, from a random solution.
+pub struct LocalSearch;
 
-use super::CoProblem;
-use crate::MAX_OPT_MILLIS;
-
-pub fn run(problem: &CoProblem) -> (Vec<usize>, u64) {
-    let time = Instant::now();
-
-    let mut rng = rand::thread_rng();
-    let mut best_solution = (0..problem.n).collect::<Vec<usize>>();
-    best_solution.shuffle(&mut rng);
+impl Solver for LocalSearch {
+    fn name(&self) -> &'static str {
+        "LS"
+    }
 
-    let mut best_solution_f = problem.eval(&best_solution);
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut initial = (0..problem.n).collect::<Vec<usize>>();
+        initial.shuffle(&mut run.rng);
+        let initial = problem.constraints.repair(&initial);
+
+        let mut best = Layout::new(problem, objective, initial);
+        run.evaluated(1, best.fitness());
+        climb(problem, objective, &mut best, 0, run);
+
+        let best_f = best.fitness();
+        (best.into_order(), best_f)
+    }
+}
 
-    #[cfg(feature = "log")]
-    let mut evals = 1;
+/// Best-first hill climbing over the swap neighborhood from `layout`, until it is a local optimum
+/// or the budget of `run` is over. `best` is the best fitness the run found before.
+pub(super) fn climb(
+    problem: &CoProblem,
+    objective: &dyn Objective,
+    layout: &mut Layout,
+    best: u128,
+    run: &mut Run,
+) {
+    let size = layout.order().len();
+    if size < 2 {
+        return;
+    }
 
     loop {
-        let mut solution = best_solution.clone();
         let mut update = false;
 
-        let size = solution.len();
         for i in 0..(size - 1) {
             for j in (i + 1)..size {
-                // perform swap
-                let v = solution[i];
-                solution[i] = solution[j];
-                solution[j] = v;
-
-                #[cfg(feature = "log")]
-                {
-                    evals += 1;
-                    log::log("time", time.elapsed().as_millis());
-                    log::log("evaluation", evals);
-                    log::log("best fitness", best_solution_f);
+                // only the feasible swaps are evaluated
+                if !problem.constraints.allows_swap(layout, i, j) {
+                    continue;
                 }
+                let improves = objective.delta_swap(problem, layout, i, j) > 0;
+                if improves {
+                    layout.swap(problem, objective, i, j);
+                }
+                run.evaluated(1, best.max(layout.fitness()));
 
-                let solution_f = problem.eval(&solution);
-
-                if solution_f > best_solution_f {
-                    best_solution_f = solution_f;
-                    best_solution = solution.clone();
+                if improves {
                     // best first selection method
                     update = true;
                     break;
                 }
 
-                if MAX_OPT_MILLIS <= time.elapsed().as_millis() {
+                if run.is_over() {
                     break;
                 }
-
-                // undo swap
-                solution[j] = solution[i];
-                solution[i] = v;
             }
-            if MAX_OPT_MILLIS <= time.elapsed().as_millis() {
+            if run.is_over() {
                 break;
             }
         }
 
-        if !update || MAX_OPT_MILLIS <= time.elapsed().as_millis() {
+        if !update || run.is_over() {
             break;
         }
     }
-
-    #[cfg(feature = "log")]
-    {
-        log::set_attr("algorithm", "LS");
-        log::write();
-    }
-
-    (best_solution, best_solution_f)
 }
 
 /*
@@ -1,7 +1,32 @@
+pub mod aco;
+pub mod cache;
+mod constraints;
 pub mod constructive;
+pub mod crossover;
 pub mod eda;
+pub mod exact;
+pub mod export;
+mod function_ordering;
+pub mod ga;
+pub mod grasp;
+mod interactions;
+mod layout;
 pub mod local_search;
+pub mod multi_objective;
+pub mod neighborhood;
+pub mod nsga2;
+mod objective;
+pub mod parallel;
 mod problem;
 pub mod sa;
+pub mod solver;
+pub mod synthetic;
+pub mod tabu;
+pub mod vns;
 
+pub use constraints::Constraints;
+pub use function_ordering::FunctionOrdering;
+pub use interactions::Interactions;
+pub use layout::Layout;
+pub use objective::{ExtTsp, Interaction, Objective};
 pub use problem::CoProblem;
@@ -0,0 +1,292 @@
+use serde::Serialize;
+
+use std::str::FromStr;
+
+use super::{CoProblem, Objective};
+
+/// Whether a criterion has to be maximized or minimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    Maximize,
+    Minimize,
+}
+
+/// A criterion of a multi-objective problem. Unlike an `Objective`, it can be minimized.
+pub trait Criterion: Sync {
+    /// Short name of the criterion, used in logs and CLI output.
+    fn name(&self) -> &'static str;
+
+    fn goal(&self) -> Goal;
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128;
+}
+
+/// Evaluates `solution` under each of the `criteria`.
+pub fn evaluate(criteria: &[&dyn Criterion], problem: &CoProblem, solution: &[usize]) -> Vec<u128> {
+    criteria.iter().map(|c| c.eval(problem, solution)).collect()
+}
+
+/// Returns `true` if the values `a` are at least as good as `b` in every criterion, and better
+/// in at least one of them.
+pub fn dominates(goals: &[Goal], a: &[u128], b: &[u128]) -> bool {
+    let mut better = false;
+    for ((goal, a), b) in goals.iter().zip(a).zip(b) {
+        let (a, b) = match goal {
+            Goal::Maximize => (a, b),
+            Goal::Minimize => (b, a),
+        };
+        if a < b {
+            return false;
+        }
+        better |= a > b;
+    }
+    better
+}
+
+/// An `Objective` as a criterion to maximize.
+pub struct Maximize<'a>(pub &'a dyn Objective);
+
+impl Criterion for Maximize<'_> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn goal(&self) -> Goal {
+        Goal::Maximize
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        self.0.eval(problem, solution)
+    }
+}
+
+/// Summed weight of the interactions `a -> b` in which `b` isn't placed right after `a`, i.e.,
+/// the number of times a branch is taken.
+pub struct TakenBranches;
+
+impl Criterion for TakenBranches {
+    fn name(&self) -> &'static str {
+        "taken-branches"
+    }
+
+    fn goal(&self) -> Goal {
+        Goal::Minimize
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        let pos = positions(problem, solution);
+        problem
+            .c
+            .edges()
+            .filter(|(a, b, _)| pos[*b] != pos[*a] + 1)
+            .map(|(_, _, w)| w as u128)
+            .sum()
+    }
+}
+
+/// Number of items none of whose successors is placed right after them, which have to end in
+/// an unconditional jump.
+pub struct InsertedJumps;
+
+impl Criterion for InsertedJumps {
+    fn name(&self) -> &'static str {
+        "jumps"
+    }
+
+    fn goal(&self) -> Goal {
+        Goal::Minimize
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        solution
+            .iter()
+            .enumerate()
+            .filter(|(k, a)| {
+                let succs = problem.c.successors(**a);
+                let next = solution.get(k + 1);
+                !succs.is_empty() && !succs.iter().any(|(b, _)| Some(b) == next)
+            })
+            .count() as u128
+    }
+}
+
+/// Size of the address range that holds the hot code: from the start of the first to the end
+/// of the last of the hottest items that account for `coverage` percent of the executions.
+/// The executions of an item are the largest of its incoming and outgoing interactions.
+pub struct HotSpread {
+    pub coverage: u64,
+}
+
+impl Default for HotSpread {
+    fn default() -> Self {
+        HotSpread { coverage: 90 }
+    }
+}
+
+impl Criterion for HotSpread {
+    fn name(&self) -> &'static str {
+        "hot-spread"
+    }
+
+    fn goal(&self) -> Goal {
+        Goal::Minimize
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        let mut incoming = vec![0; problem.n];
+        for (_, b, w) in problem.c.edges() {
+            incoming[b] += w;
+        }
+        let mut counts = (0..problem.n)
+            .map(|a| (problem.c.row_sum(a).max(incoming[a]), a))
+            .collect::<Vec<(u64, usize)>>();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+
+        let total: u128 = counts.iter().map(|(c, _)| *c as u128).sum();
+        let goal = total * self.coverage as u128 / 100;
+
+        let mut is_hot = vec![false; problem.n];
+        let mut covered = 0;
+        for (count, item) in counts {
+            if covered >= goal || count == 0 {
+                break;
+            }
+            is_hot[item] = true;
+            covered += count as u128;
+        }
+
+        // first and last address of the hot code
+        let mut addr = 0;
+        let mut range: Option<(u64, u64)> = None;
+        for item in solution {
+            let end = addr + problem.s[*item] as u64;
+            if is_hot[*item] {
+                range = Some((range.map(|r| r.0).unwrap_or(addr), end));
+            }
+            addr = end;
+        }
+
+        range.map(|(start, end)| end - start).unwrap_or(0) as u128
+    }
+}
+
+fn positions(problem: &CoProblem, solution: &[usize]) -> Vec<usize> {
+    let mut pos = vec![0; problem.n];
+    for (k, item) in solution.iter().enumerate() {
+        pos[*item] = k;
+    }
+    pos
+}
+
+/// A solution of the Pareto front and its value under each criterion.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParetoPoint {
+    pub solution: Vec<usize>,
+    pub values: Vec<u128>,
+}
+
+/// Set of non-dominated solutions found by a multi-objective algorithm.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParetoFront {
+    /// Name of each criterion
+    pub criteria: Vec<&'static str>,
+    pub goals: Vec<Goal>,
+    pub points: Vec<ParetoPoint>,
+}
+
+/// Rule to pick a single solution of a Pareto front.
+#[derive(Debug, Clone)]
+pub enum Selection {
+    /// Maximizes the weighted sum of the normalized criteria (see `ParetoFront::normalized`).
+    WeightedSum(Vec<f64>),
+    /// The solution closest (in Chebyshev distance) to the ideal point once the criteria are
+    /// normalized, a common approximation of the knee of the front.
+    Knee,
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    /// Parses `knee`, or `weighted:w1,w2,...` with one weight per criterion.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "knee" {
+            return Ok(Selection::Knee);
+        }
+
+        match s.strip_prefix("weighted:") {
+            Some(weights) => weights
+                .split(',')
+                .map(|w| w.trim().parse::<f64>().map_err(|e| format!("{w}: {e}")))
+                .collect::<Result<Vec<f64>, String>>()
+                .map(Selection::WeightedSum),
+            None => Err(format!(
+                "Invalid selection rule {s}. Valid options are: knee and weighted:w1,w2,..."
+            )),
+        }
+    }
+}
+
+impl ParetoFront {
+    /// Values of each point scaled to `[0, 1]` over the front, where `1` is the best value of
+    /// the criterion in the front.
+    pub fn normalized(&self) -> Vec<Vec<f64>> {
+        let num_criteria = self.criteria.len();
+        let min_max = (0..num_criteria)
+            .map(|c| {
+                let values = self.points.iter().map(|p| p.values[c]);
+                (values.clone().min().unwrap_or(0), values.max().unwrap_or(0))
+            })
+            .collect::<Vec<(u128, u128)>>();
+
+        self.points
+            .iter()
+            .map(|p| {
+                (0..num_criteria)
+                    .map(|c| {
+                        let (min, max) = min_max[c];
+                        if max == min {
+                            return 1.0;
+                        }
+                        let x = (p.values[c] - min) as f64 / (max - min) as f64;
+                        match self.goals[c] {
+                            Goal::Maximize => x,
+                            Goal::Minimize => 1.0 - x,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the index of the point picked by the given rule.
+    pub fn select(&self, rule: &Selection) -> Result<usize, String> {
+        let score = |values: &Vec<f64>| -> f64 {
+            match rule {
+                Selection::WeightedSum(weights) => {
+                    values.iter().zip(weights.iter()).map(|(v, w)| v * w).sum()
+                }
+                // the negated distance to the ideal point (all ones)
+                Selection::Knee => -values.iter().map(|v| 1.0 - v).fold(0.0, f64::max),
+            }
+        };
+
+        if let Selection::WeightedSum(weights) = rule {
+            if weights.len() != self.criteria.len() {
+                return Err(format!(
+                    "expected {} weights, found {}",
+                    self.criteria.len(),
+                    weights.len()
+                ));
+            }
+        }
+
+        self.normalized()
+            .iter()
+            .map(score)
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(idx, _)| idx)
+            .ok_or_else(|| "the front is empty".to_string())
+    }
+}
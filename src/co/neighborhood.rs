@@ -0,0 +1,200 @@
+use rand::prelude::*;
+
+use std::str::FromStr;
+
+use super::{CoProblem, Layout, Objective};
+
+/// A move from a solution to one of its neighbors. Positions refer to the solution the move is
+/// applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Move {
+    /// Swaps the items at two positions.
+    Swap(usize, usize),
+    /// Moves the item at position `from` so that it ends up at position `to`.
+    Insert(usize, usize),
+    /// Moves the `len` items starting at position `start` so that they start at position `to`,
+    /// keeping their order (Or-opt).
+    SegmentMove { start: usize, len: usize, to: usize },
+    /// Reverses the items at positions `i..=j` (2-opt).
+    Reverse(usize, usize),
+}
+
+impl Move {
+    /// Returns `true` if the move keeps the (feasible) `layout` feasible.
+    pub fn is_allowed(self, problem: &CoProblem, layout: &Layout) -> bool {
+        let constraints = &problem.constraints;
+        match self {
+            Move::Swap(i, j) => constraints.allows_swap(layout, i, j),
+            Move::Insert(from, to) => constraints.allows_insert(layout, from, to),
+            Move::SegmentMove { start, len, to } => {
+                constraints.allows_move_segment(layout, start, len, to)
+            }
+            Move::Reverse(i, j) => constraints.allows_reverse(layout, i, j),
+        }
+    }
+
+    /// Change in fitness produced by applying the move to `layout`.
+    pub fn delta(self, problem: &CoProblem, objective: &dyn Objective, layout: &Layout) -> i128 {
+        match self {
+            Move::Swap(i, j) => objective.delta_swap(problem, layout, i, j),
+            Move::Insert(from, to) => objective.delta_insert(problem, layout, from, to),
+            Move::SegmentMove { start, len, to } => {
+                objective.delta_move_segment(problem, layout, start, len, to)
+            }
+            Move::Reverse(i, j) => objective.delta_reverse(problem, layout, i, j),
+        }
+    }
+
+    pub fn apply(self, problem: &CoProblem, objective: &dyn Objective, layout: &mut Layout) {
+        match self {
+            Move::Swap(i, j) => layout.swap(problem, objective, i, j),
+            Move::Insert(from, to) => layout.insert(problem, objective, from, to),
+            Move::SegmentMove { start, len, to } => {
+                layout.move_segment(problem, objective, start, len, to)
+            }
+            Move::Reverse(i, j) => layout.reverse(problem, objective, i, j),
+        }
+    }
+
+    /// The solution obtained by applying the move to `order`.
+    pub fn neighbor(self, order: &[usize]) -> Vec<usize> {
+        let mut neighbor = order.to_vec();
+        match self {
+            Move::Swap(i, j) => neighbor.swap(i, j),
+            Move::Insert(from, to) => {
+                let item = neighbor.remove(from);
+                neighbor.insert(to, item);
+            }
+            Move::SegmentMove { start, len, to } => {
+                let segment = neighbor.drain(start..start + len).collect::<Vec<usize>>();
+                neighbor.splice(to..to, segment);
+            }
+            Move::Reverse(i, j) => neighbor[i..=j].reverse(),
+        }
+        neighbor
+    }
+
+    /// Smallest range of positions that contains every item the move changes of place.
+    pub fn span(self) -> (usize, usize) {
+        match self {
+            Move::Swap(i, j) | Move::Insert(i, j) | Move::Reverse(i, j) => (i.min(j), i.max(j)),
+            Move::SegmentMove { start, len, to } => (start.min(to), start.max(to) + len - 1),
+        }
+    }
+}
+
+/// A neighborhood structure: the set of moves of a given kind from any solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighborhood {
+    Swap,
+    Insert,
+    /// Moves of segments of up to `max_len` items.
+    SegmentMove {
+        max_len: usize,
+    },
+    Reversal,
+}
+
+impl FromStr for Neighborhood {
+    type Err = String;
+
+    /// Parses `swap`, `insert`, `segment:L` (segments of up to `L` items) or `reversal`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => return Ok(Neighborhood::Swap),
+            "insert" => return Ok(Neighborhood::Insert),
+            "reversal" => return Ok(Neighborhood::Reversal),
+            _ => (),
+        }
+
+        match s.strip_prefix("segment:") {
+            Some(len) => match len.trim().parse::<usize>() {
+                Ok(max_len) if max_len > 0 => Ok(Neighborhood::SegmentMove { max_len }),
+                _ => Err(format!("Invalid maximum segment length {len}")),
+            },
+            None => Err(format!(
+                "Invalid neighborhood {s}. Valid options are: swap, insert, segment:L and reversal"
+            )),
+        }
+    }
+}
+
+impl Neighborhood {
+    /// Every move of the neighborhood of the solutions of length `n`, feasible or not, in a
+    /// fixed order.
+    pub fn moves(self, n: usize) -> Box<dyn Iterator<Item = Move>> {
+        match self {
+            Neighborhood::Swap => {
+                Box::new((0..n).flat_map(move |i| ((i + 1)..n).map(move |j| Move::Swap(i, j))))
+            }
+            Neighborhood::Insert => Box::new((0..n).flat_map(move |from| {
+                (0..n)
+                    .filter(move |to| *to != from)
+                    .map(move |to| Move::Insert(from, to))
+            })),
+            Neighborhood::SegmentMove { max_len } => {
+                Box::new((1..=max_len.min(n.saturating_sub(1))).flat_map(move |len| {
+                    (0..=(n - len)).flat_map(move |start| {
+                        (0..=(n - len))
+                            .filter(move |to| *to != start)
+                            .map(move |to| Move::SegmentMove { start, len, to })
+                    })
+                }))
+            }
+            Neighborhood::Reversal => {
+                Box::new((0..n).flat_map(move |i| ((i + 1)..n).map(move |j| Move::Reverse(i, j))))
+            }
+        }
+    }
+
+    /// Returns `true` if some move of the neighborhood keeps the (feasible) `layout` feasible.
+    /// Every move has an inverse in its neighborhood, so a layout without feasible moves is the
+    /// only solution the neighborhood can reach from it.
+    pub fn has_allowed_move(self, problem: &CoProblem, layout: &Layout) -> bool {
+        self.moves(layout.order().len())
+            .any(|mv| mv.is_allowed(problem, layout))
+    }
+
+    /// A random move of the neighborhood of the solutions of length `n` (which must be at least
+    /// 2), feasible or not.
+    pub fn random_move(self, n: usize, rng: &mut impl Rng) -> Move {
+        assert!(n >= 2, "Random move of a solution of length {}", n);
+        match self {
+            Neighborhood::Swap => {
+                let (i, j) = random_pair(n, rng);
+                Move::Swap(i, j)
+            }
+            Neighborhood::Insert => {
+                let (from, to) = random_pair(n, rng);
+                Move::Insert(from, to)
+            }
+            Neighborhood::SegmentMove { max_len } => {
+                let len = rng.gen_range(1..=max_len.min(n - 1));
+                let (start, to) = random_pair(n - len + 1, rng);
+                Move::SegmentMove { start, len, to }
+            }
+            Neighborhood::Reversal => {
+                let (i, j) = random_pair(n, rng);
+                Move::Reverse(i.min(j), i.max(j))
+            }
+        }
+    }
+}
+
+/// Returns two different random positions of a solution of the given length, which must be at
+/// least 2.
+pub fn random_pair(len: usize, rng: &mut impl Rng) -> (usize, usize) {
+    assert!(
+        len >= 2,
+        "Random pair of positions of a solution of length {}",
+        len
+    );
+    let i = rng.gen_range(0..len);
+    let mut j = rng.gen_range(0..len);
+
+    while i == j {
+        j = rng.gen_range(0..len);
+    }
+
+    (i, j)
+}
@@ -0,0 +1,216 @@
+use rand::prelude::*;
+
+use std::cmp::Ordering;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::crossover::order_crossover;
+use super::multi_objective::{dominates, evaluate, Criterion, Goal, ParetoFront, ParetoPoint};
+use super::solver::Run;
+use super::CoProblem;
+
+/// NSGA-II over permutations, with order crossover and insertion mutation.
+pub struct Nsga2 {
+    pub pop_size: usize,
+    /// Probability of mutating each child
+    pub mutation_prob: f64,
+}
+
+impl Default for Nsga2 {
+    fn default() -> Self {
+        Nsga2 {
+            pop_size: 100,
+            mutation_prob: 0.2,
+        }
+    }
+}
+
+impl Nsga2 {
+    /// Runs until the budget of `run` is over (its target fitness is ignored). Returns the
+    /// non-dominated solutions of the last population.
+    pub fn run(
+        &self,
+        problem: &CoProblem,
+        criteria: &[&dyn Criterion],
+        run: &mut Run,
+    ) -> ParetoFront {
+        let (pop_size, mutation_prob) = (self.pop_size, self.mutation_prob);
+        let rng = &mut run.rng;
+        let goals = criteria.iter().map(|c| c.goal()).collect::<Vec<Goal>>();
+
+        let mut pop = (0..pop_size)
+            .map(|_| {
+                let mut s = (0..problem.n).collect::<Vec<usize>>();
+                s.shuffle(rng);
+                let s = problem.constraints.repair(&s);
+                let values = evaluate(criteria, problem, &s);
+                ParetoPoint {
+                    solution: s,
+                    values,
+                }
+            })
+            .collect::<Vec<ParetoPoint>>();
+
+        let (mut rank, mut crowding) = rank_and_crowding(&pop, &goals);
+        run.count(pop_size);
+
+        while !run.is_over() {
+            let rng = &mut run.rng;
+            // generate the offspring
+            let mut offspring = Vec::with_capacity(pop_size);
+            while offspring.len() < pop_size {
+                let p1 = tournament(&rank, &crowding, rng);
+                let p2 = tournament(&rank, &crowding, rng);
+                let mut child = order_crossover(&pop[p1].solution, &pop[p2].solution, rng);
+
+                if child.len() > 1 && rng.gen::<f64>() < mutation_prob {
+                    let from = rng.gen_range(0..child.len());
+                    let to = rng.gen_range(0..child.len());
+                    let item = child.remove(from);
+                    child.insert(to, item);
+                }
+
+                let child = problem.constraints.repair(&child);
+                let values = evaluate(criteria, problem, &child);
+                offspring.push(ParetoPoint {
+                    solution: child,
+                    values,
+                });
+            }
+
+            // the best `pop_size` solutions of the parents and offspring survive
+            pop.append(&mut offspring);
+            let (all_rank, all_crowding) = rank_and_crowding(&pop, &goals);
+            let mut order = (0..pop.len()).collect::<Vec<usize>>();
+            order.sort_by(|a, b| compare(*a, *b, &all_rank, &all_crowding));
+            order.truncate(pop_size);
+
+            rank = order.iter().map(|i| all_rank[*i]).collect();
+            crowding = order.iter().map(|i| all_crowding[*i]).collect();
+            let mut pop_opt = pop
+                .into_iter()
+                .map(Some)
+                .collect::<Vec<Option<ParetoPoint>>>();
+            pop = order.iter().map(|i| pop_opt[*i].take().unwrap()).collect();
+
+            run.count(pop_size);
+            #[cfg(feature = "log")]
+            {
+                log::log("time", run.millis());
+                log::log("evaluation", run.evaluations());
+                log::log("front size", rank.iter().filter(|r| **r == 0).count());
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("algorithm", "NSGA2");
+            log::set_attr("pop size", pop_size);
+            log::set_attr("mutation prob", mutation_prob);
+            log::write();
+        }
+
+        // the first front, keeping a single solution for each vector of values
+        let mut points: Vec<ParetoPoint> = vec![];
+        for (point, r) in pop.into_iter().zip(rank) {
+            if r == 0 && !points.iter().any(|p| p.values == point.values) {
+                points.push(point);
+            }
+        }
+
+        ParetoFront {
+            criteria: criteria.iter().map(|c| c.name()).collect(),
+            goals,
+            points,
+        }
+    }
+}
+
+/// Better rank first, then larger crowding distance.
+fn compare(a: usize, b: usize, rank: &[usize], crowding: &[f64]) -> Ordering {
+    rank[a]
+        .cmp(&rank[b])
+        .then_with(|| crowding[b].partial_cmp(&crowding[a]).unwrap())
+}
+
+/// Binary tournament selection, returns the index of the winner.
+fn tournament(rank: &[usize], crowding: &[f64], rng: &mut impl Rng) -> usize {
+    let a = rng.gen_range(0..rank.len());
+    let b = rng.gen_range(0..rank.len());
+    match compare(a, b, rank, crowding) {
+        Ordering::Greater => b,
+        _ => a,
+    }
+}
+
+/// Fast non-dominated sorting. Returns the front of each solution (`0` is the non-dominated
+/// one) and its crowding distance inside the front.
+fn rank_and_crowding(pop: &[ParetoPoint], goals: &[Goal]) -> (Vec<usize>, Vec<f64>) {
+    let n = pop.len();
+
+    let mut dominated_by = vec![0; n];
+    let mut dominates_list = vec![vec![]; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if dominates(goals, &pop[i].values, &pop[j].values) {
+                dominates_list[i].push(j);
+                dominated_by[j] += 1;
+            } else if dominates(goals, &pop[j].values, &pop[i].values) {
+                dominates_list[j].push(i);
+                dominated_by[i] += 1;
+            }
+        }
+    }
+
+    let mut rank = vec![0; n];
+    let mut crowding = vec![0.0; n];
+    let mut front = (0..n)
+        .filter(|i| dominated_by[*i] == 0)
+        .collect::<Vec<usize>>();
+    let mut r = 0;
+
+    while !front.is_empty() {
+        crowding_distance(pop, &front, &mut crowding);
+
+        let mut next = vec![];
+        for i in front {
+            rank[i] = r;
+            for j in dominates_list[i].iter() {
+                dominated_by[*j] -= 1;
+                if dominated_by[*j] == 0 {
+                    next.push(*j);
+                }
+            }
+        }
+        front = next;
+        r += 1;
+    }
+
+    (rank, crowding)
+}
+
+fn crowding_distance(pop: &[ParetoPoint], front: &[usize], crowding: &mut [f64]) {
+    let num_criteria = pop[front[0]].values.len();
+    front.iter().for_each(|i| crowding[*i] = 0.0);
+
+    let mut sorted = front.to_vec();
+    for c in 0..num_criteria {
+        sorted.sort_by_key(|i| pop[*i].values[c]);
+
+        let (min, max) = (
+            pop[sorted[0]].values[c],
+            pop[sorted[sorted.len() - 1]].values[c],
+        );
+        crowding[sorted[0]] = f64::INFINITY;
+        crowding[sorted[sorted.len() - 1]] = f64::INFINITY;
+        if max == min {
+            continue;
+        }
+
+        for k in 1..sorted.len().saturating_sub(1) {
+            let gap = pop[sorted[k + 1]].values[c] - pop[sorted[k - 1]].values[c];
+            crowding[sorted[k]] += gap as f64 / (max - min) as f64;
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use super::neighborhood::Move;
+use super::{CoProblem, Layout};
+
+/// A fitness function to maximize over the solutions of a `CoProblem`.
+///
+/// Only `eval` is required. The incremental methods fall back to evaluating the whole
+/// neighbor, objectives that can do better should override them.
+pub trait Objective: Sync {
+    /// Short name of the objective, used in logs and CLI output.
+    fn name(&self) -> &'static str;
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128;
+
+    /// Upper bound of the fitness of any solution of `problem`, computed with checked
+    /// arithmetic. `None` if the bound doesn't fit in a `u128`, in which case the fitness of some
+    /// solutions can't be computed.
+    fn max_fitness(&self, problem: &CoProblem) -> Option<u128>;
+
+    /// Upper bound of the fitness of the solutions that start with the items of `prefix`, used
+    /// to prune the search of `exact::branch_and_bound`. Defaults to `max_fitness`.
+    fn bound_prefix(&self, problem: &CoProblem, _prefix: &[usize]) -> u128 {
+        self.max_fitness(problem).unwrap_or(u128::MAX)
+    }
+
+    /// Fitness of `layout`, which can make use of its cached data.
+    fn eval_layout(&self, problem: &CoProblem, layout: &Layout) -> u128 {
+        self.eval(problem, layout.order())
+    }
+
+    /// Change in fitness produced by swapping the items at positions `i` and `j` of `layout`.
+    fn delta_swap(&self, problem: &CoProblem, layout: &Layout, i: usize, j: usize) -> i128 {
+        let mut neighbor = layout.order().to_vec();
+        neighbor.swap(i, j);
+        self.eval(problem, &neighbor) as i128 - layout.fitness() as i128
+    }
+
+    /// Change in fitness produced by moving the item at position `from` of `layout` to `to`.
+    fn delta_insert(&self, problem: &CoProblem, layout: &Layout, from: usize, to: usize) -> i128 {
+        let mut neighbor = layout.order().to_vec();
+        let item = neighbor.remove(from);
+        neighbor.insert(to, item);
+        self.eval(problem, &neighbor) as i128 - layout.fitness() as i128
+    }
+
+    /// Change in fitness produced by moving the `len` items starting at position `start` of
+    /// `layout` so that they start at position `to`.
+    fn delta_move_segment(
+        &self,
+        problem: &CoProblem,
+        layout: &Layout,
+        start: usize,
+        len: usize,
+        to: usize,
+    ) -> i128 {
+        let neighbor = Move::SegmentMove { start, len, to }.neighbor(layout.order());
+        self.eval(problem, &neighbor) as i128 - layout.fitness() as i128
+    }
+
+    /// Change in fitness produced by reversing the items at positions `i..=j` of `layout`.
+    fn delta_reverse(&self, problem: &CoProblem, layout: &Layout, i: usize, j: usize) -> i128 {
+        let neighbor = Move::Reverse(i, j).neighbor(layout.order());
+        self.eval(problem, &neighbor) as i128 - layout.fitness() as i128
+    }
+}
+
+/// The original objective of the problem: the interaction of every pair of items weighted by
+/// how close they are placed (see `CoProblem::eval`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Interaction;
+
+impl Objective for Interaction {
+    fn name(&self) -> &'static str {
+        "interaction"
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        problem.eval(solution)
+    }
+
+    fn max_fitness(&self, problem: &CoProblem) -> Option<u128> {
+        problem.max_fitness()
+    }
+
+    fn bound_prefix(&self, problem: &CoProblem, prefix: &[usize]) -> u128 {
+        problem.bound_prefix(prefix)
+    }
+
+    fn eval_layout(&self, problem: &CoProblem, layout: &Layout) -> u128 {
+        layout.interaction_fitness(problem)
+    }
+
+    fn delta_swap(&self, problem: &CoProblem, layout: &Layout, i: usize, j: usize) -> i128 {
+        problem.delta_swap(layout, i, j)
+    }
+
+    fn delta_insert(&self, problem: &CoProblem, layout: &Layout, from: usize, to: usize) -> i128 {
+        problem.delta_insert(layout, from, to)
+    }
+
+    fn delta_move_segment(
+        &self,
+        problem: &CoProblem,
+        layout: &Layout,
+        start: usize,
+        len: usize,
+        to: usize,
+    ) -> i128 {
+        problem.delta_move_segment(layout, start, len, to)
+    }
+
+    fn delta_reverse(&self, problem: &CoProblem, layout: &Layout, i: usize, j: usize) -> i128 {
+        problem.delta_reverse(layout, i, j)
+    }
+}
+
+/// Extended TSP score, the objective optimized by LLVM's block placement
+/// (`llvm/lib/Transforms/Utils/CodeLayout.cpp`).
+///
+/// Every branch `a -> b` with weight `w` adds `fallthrough_weight * w` if `b` is placed right
+/// after `a`. Otherwise, if the jump is forward and its distance `d` (in size units, from the end
+/// of `a` to the start of `b`) is below `forward_distance`, it adds
+/// `forward_weight * w * (1 - d / forward_distance)`. Backward jumps are scored the same way with
+/// `backward_weight` and `backward_distance`.
+///
+/// Weights are integers, the defaults are LLVM's (1.0, 0.1 and 0.1) scaled by 10.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtTsp {
+    pub fallthrough_weight: u64,
+    pub forward_weight: u64,
+    pub backward_weight: u64,
+    pub forward_distance: u64,
+    pub backward_distance: u64,
+}
+
+impl Default for ExtTsp {
+    fn default() -> Self {
+        ExtTsp {
+            fallthrough_weight: 10,
+            forward_weight: 1,
+            backward_weight: 1,
+            forward_distance: 1024,
+            backward_distance: 640,
+        }
+    }
+}
+
+impl ExtTsp {
+    /// Score of a single branch, where `src_end` is the address right after the source item and
+    /// `dst` the address of the target item.
+    fn jump_score(&self, fallthrough: bool, src_end: u64, dst: u64, weight: u64) -> u128 {
+        let weight = weight as u128;
+        let (factor, dist, window) = if fallthrough {
+            return self.fallthrough_weight as u128 * weight;
+        } else if src_end <= dst {
+            (self.forward_weight, dst - src_end, self.forward_distance)
+        } else {
+            (self.backward_weight, src_end - dst, self.backward_distance)
+        };
+
+        if dist >= window {
+            0
+        } else {
+            factor as u128 * weight * (window - dist) as u128 / window as u128
+        }
+    }
+
+    /// Evaluates the score given the start address of each position.
+    fn score(&self, problem: &CoProblem, pos: &[usize], prefix: &[u64]) -> u128 {
+        problem
+            .c
+            .edges()
+            .map(|(a, b, w)| {
+                let fallthrough = pos[b] == pos[a] + 1;
+                self.jump_score(fallthrough, prefix[pos[a] + 1], prefix[pos[b]], w)
+            })
+            .sum()
+    }
+}
+
+impl Objective for ExtTsp {
+    fn name(&self) -> &'static str {
+        "ext-tsp"
+    }
+
+    fn eval(&self, problem: &CoProblem, solution: &[usize]) -> u128 {
+        let mut pos = vec![0; problem.n];
+        let mut prefix = Vec::with_capacity(problem.n + 1);
+        prefix.push(0);
+        for (k, item) in solution.iter().enumerate() {
+            pos[*item] = k;
+            prefix.push(prefix[k] + problem.s[*item] as u64);
+        }
+
+        self.score(problem, &pos, &prefix)
+    }
+
+    /// Every branch scores at most its weight times the largest of the factors.
+    fn max_fitness(&self, problem: &CoProblem) -> Option<u128> {
+        let factor = self
+            .fallthrough_weight
+            .max(self.forward_weight)
+            .max(self.backward_weight);
+        problem.c.edges().try_fold(0u128, |sum, (_, _, w)| {
+            sum.checked_add((w as u128).checked_mul(factor as u128)?)
+        })
+    }
+
+    /// The branches between placed items score exactly. The rest score as if their jumps were
+    /// as short as the prefix allows: the forward jumps from a placed item land right after the
+    /// prefix, and the backward jumps to a placed item start from an item placed right after it.
+    fn bound_prefix(&self, problem: &CoProblem, prefix: &[usize]) -> u128 {
+        let mut pos = vec![None; problem.n];
+        let mut addr = Vec::with_capacity(prefix.len() + 1);
+        addr.push(0);
+        for (k, item) in prefix.iter().enumerate() {
+            pos[*item] = Some(k);
+            addr.push(addr[k] + problem.s[*item] as u64);
+        }
+        let end = addr[prefix.len()];
+        let factor = self
+            .fallthrough_weight
+            .max(self.forward_weight)
+            .max(self.backward_weight);
+
+        problem
+            .c
+            .edges()
+            .map(|(a, b, w)| match (pos[a], pos[b]) {
+                (Some(pa), Some(pb)) => self.jump_score(pb == pa + 1, addr[pa + 1], addr[pb], w),
+                // the last item of the prefix can still fall through
+                (Some(pa), None) if pa + 1 == prefix.len() => self
+                    .jump_score(true, end, end, w)
+                    .max(self.jump_score(false, end, end, w)),
+                (Some(pa), None) => self.jump_score(false, addr[pa + 1], end, w),
+                (None, Some(pb)) => self.jump_score(false, end + problem.s[a] as u64, addr[pb], w),
+                (None, None) => w as u128 * factor as u128,
+            })
+            .sum()
+    }
+
+    fn eval_layout(&self, problem: &CoProblem, layout: &Layout) -> u128 {
+        self.score(problem, &layout.pos, &layout.prefix)
+    }
+}
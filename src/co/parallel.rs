@@ -0,0 +1,274 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Barrier, Mutex};
+use std::thread;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::solver::{Run, Solver};
+use super::{CoProblem, Objective};
+
+/// A solution and its fitness.
+pub type Individual = (Vec<usize>, u128);
+
+/// A solver that evolves a population one generation after another, so that its population can
+/// be split into islands that exchange their best solutions (see `Islands`).
+pub trait Evolutionary: Solver {
+    /// Population, and whatever else the solver keeps between generations
+    type Population: Send;
+
+    /// A new random population.
+    fn init(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> Self::Population;
+
+    /// Evolves the population one generation.
+    fn generation(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        pop: &mut Self::Population,
+        run: &mut Run,
+    );
+
+    /// Best solution found by the population so far.
+    fn best(&self, pop: &Self::Population) -> Individual;
+
+    /// Copies of the `count` best solutions of the population, to be sent to other islands.
+    fn emigrants(&self, pop: &Self::Population, count: usize) -> Vec<Individual>;
+
+    /// Replaces the worst solutions of the population with the ones from other islands.
+    fn immigrate(&self, pop: &mut Self::Population, migrants: Vec<Individual>);
+}
+
+/// Islands each island receives migrants from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// Island `k` receives from island `k - 1`, and the first one from the last one
+    Ring,
+    /// Each island receives from all the others
+    Complete,
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ring" => Ok(Topology::Ring),
+            "complete" => Ok(Topology::Complete),
+            _ => Err(format!(
+                "Invalid topology {s}. Valid options are: ring and complete"
+            )),
+        }
+    }
+}
+
+/// When and how the islands exchange solutions.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Number of generations between migrations
+    pub interval: usize,
+    /// Number of the best solutions each island sends
+    pub migrants: usize,
+    pub topology: Topology,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        Migration {
+            interval: 10,
+            migrants: 2,
+            topology: Topology::Ring,
+        }
+    }
+}
+
+/// Independent runs of a solver, each in its own thread and with its own seed, that share the
+/// budget of the run. The best solution of all of them is returned.
+pub struct MultiStart {
+    pub solver: Box<dyn Solver>,
+    pub threads: usize,
+}
+
+impl Solver for MultiStart {
+    fn name(&self) -> &'static str {
+        self.solver.name()
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let runs = run.split(self.threads.max(1));
+
+        let results = thread::scope(|scope| {
+            let handles = runs
+                .into_iter()
+                .map(|mut start| {
+                    scope.spawn(move || {
+                        let best = self.solver.search(problem, objective, &mut start);
+                        #[cfg(feature = "log")]
+                        let best = (best, log::take());
+                        (best, start)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        #[cfg(feature = "log")]
+        let results = merge_logs(results);
+        let (best, runs) = unzip_best(results);
+        run.join(&runs, best.1);
+
+        #[cfg(feature = "log")]
+        log::set_attr("threads", self.threads);
+
+        best
+    }
+}
+
+/// Island model of an evolutionary solver: the population of each island evolves in its own
+/// thread, and every `migration.interval` generations the islands stop to send copies of their
+/// best solutions to their neighbours, which replace their worst ones.
+pub struct Islands<E> {
+    pub solver: E,
+    pub islands: usize,
+    pub migration: Migration,
+}
+
+impl<E: Evolutionary> Solver for Islands<E> {
+    fn name(&self) -> &'static str {
+        self.solver.name()
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        // a single item has no neighbors, so the generations of the islands can't move it
+        if problem.n < 2 {
+            return self.solver.search(problem, objective, run);
+        }
+
+        let runs = run.split(self.islands.max(1));
+        let count = runs.len();
+
+        let barrier = Barrier::new(count);
+        // emigrants of each island in the current migration
+        let board = Mutex::new(vec![vec![]; count]);
+        // raised when the budget of any island is over, so that all of them stop together
+        let over = AtomicBool::new(false);
+
+        let results = thread::scope(|scope| {
+            let handles = runs
+                .into_iter()
+                .enumerate()
+                .map(|(k, mut island)| {
+                    let (barrier, board, over) = (&barrier, &board, &over);
+
+                    scope.spawn(move || {
+                        let solver = &self.solver;
+                        let mut pop = solver.init(problem, objective, &mut island);
+
+                        loop {
+                            for _ in 0..self.migration.interval.max(1) {
+                                if island.is_over() {
+                                    break;
+                                }
+                                solver.generation(problem, objective, &mut pop, &mut island);
+                            }
+                            if island.is_over() {
+                                over.store(true, Ordering::Relaxed);
+                            }
+
+                            board.lock().unwrap()[k] =
+                                solver.emigrants(&pop, self.migration.migrants);
+                            barrier.wait();
+
+                            let migrants = {
+                                let board = board.lock().unwrap();
+                                match self.migration.topology {
+                                    Topology::Ring => board[(k + count - 1) % count].clone(),
+                                    Topology::Complete => (0..count)
+                                        .filter(|i| *i != k)
+                                        .flat_map(|i| board[i].iter().cloned())
+                                        .collect(),
+                                }
+                            };
+                            let stop = over.load(Ordering::Relaxed);
+                            // no island publishes again before all of them have read the board
+                            barrier.wait();
+
+                            if stop {
+                                break;
+                            }
+                            solver.immigrate(&mut pop, migrants);
+                        }
+
+                        let best = solver.best(&pop);
+                        #[cfg(feature = "log")]
+                        let best = (best, log::take());
+                        (best, island)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        #[cfg(feature = "log")]
+        let results = merge_logs(results);
+        let (best, runs) = unzip_best(results);
+        run.join(&runs, best.1);
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("islands", self.islands);
+            log::set_attr("migration interval", self.migration.interval);
+            log::set_attr("migrants", self.migration.migrants);
+            log::set_attr("topology", format!("{:?}", self.migration.topology));
+        }
+
+        best
+    }
+}
+
+/// Merges the data logged by the threads of the parallel runs, in the order of the runs, into
+/// the data of the current thread, which writes the log.
+#[cfg(feature = "log")]
+fn merge_logs(results: Vec<((Individual, log::TabledData), Run)>) -> Vec<(Individual, Run)> {
+    results
+        .into_iter()
+        .map(|((best, data), run)| {
+            log::merge(data);
+            (best, run)
+        })
+        .collect()
+}
+
+/// Best solution of the parallel runs (the first one on ties), and the runs.
+fn unzip_best(results: Vec<(Individual, Run)>) -> (Individual, Vec<Run>) {
+    let (solutions, runs): (Vec<Individual>, Vec<Run>) = results.into_iter().unzip();
+    let best = solutions
+        .into_iter()
+        .reduce(|best, s| if s.1 > best.1 { s } else { best })
+        .unwrap();
+    (best, runs)
+}
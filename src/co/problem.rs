@@ -1,161 +1,538 @@
-use crate::profdata::{Function, Metadata};
+use super::constraints::ConstraintLists;
+use super::layout::{min_max, Layout};
+use super::{Constraints, Interactions};
+use crate::profdata::Function;
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
 #[derive(Serialize, Deserialize)]
+#[serde(try_from = "RawCoProblem")]
 pub struct CoProblem {
-    pub c: Vec<Vec<u64>>,
+    /// Interaction matrix, serialized as a list of `(from, to, weight)` edges
+    #[serde(rename = "edges")]
+    pub c: Interactions,
     pub s: Vec<usize>,
     pub n: usize,
+    /// Constraints that the solutions must satisfy
+    #[serde(skip_serializing_if = "Constraints::is_empty")]
+    pub constraints: Constraints,
+}
+
+/// Instance format as read from disk. Besides the edge list, the dense `c` matrix written by
+/// older versions of the generator is also accepted.
+#[derive(Deserialize)]
+struct RawCoProblem {
+    #[serde(default)]
+    edges: Option<Vec<(usize, usize, u64)>>,
+    #[serde(default)]
+    c: Option<Vec<Vec<u64>>>,
+    s: Vec<usize>,
+    n: usize,
+    #[serde(default)]
+    constraints: ConstraintLists,
+}
+
+impl TryFrom<RawCoProblem> for CoProblem {
+    type Error = String;
+
+    fn try_from(raw: RawCoProblem) -> Result<Self, Self::Error> {
+        if raw.s.len() != raw.n {
+            return Err(format!("expected {} sizes, found {}", raw.n, raw.s.len()));
+        }
+
+        let n = raw.n;
+        let c = match (raw.edges, raw.c) {
+            (Some(edges), _) => {
+                if let Some((a, b, _)) = edges.iter().find(|(a, b, _)| *a >= n || *b >= n) {
+                    return Err(format!("edge ({a}, {b}) out of bounds for {n} items"));
+                }
+                Interactions::from_edges(n, edges)
+            }
+            (None, Some(dense)) => {
+                if dense.len() != n || dense.iter().any(|row| row.len() != n) {
+                    return Err(format!("the `c` matrix is not {n}x{n}"));
+                }
+                Interactions::from_dense(&dense)
+            }
+            (None, None) => return Err("missing field `edges`".to_string()),
+        };
+
+        let constraints = Constraints::from_lists(n, raw.constraints)?;
+
+        Ok(CoProblem {
+            c,
+            s: raw.s,
+            n,
+            constraints,
+        })
+    }
 }
 
 impl CoProblem {
-    pub fn eval(&self, solution: &[usize]) -> u64 {
+    pub fn eval(&self, solution: &[usize]) -> u128 {
         // original: let s_sum = self.s.iter().fold(0, |sum, v| sum + v);
         let s_sum: usize = self.s.iter().sum();
         // println!("s_sum: {s_sum}");
 
+        // prefix[k] is the summed size of the items in positions `0..k`
+        let mut prefix = Vec::with_capacity(self.n + 1);
+        let mut pos = vec![0; self.n];
+        prefix.push(0);
+        for (k, item) in solution.iter().enumerate() {
+            prefix.push(prefix[k] + self.s[*item]);
+            pos[*item] = k;
+        }
+
+        // only the nonzero interactions contribute to the fitness
         let mut f = 0;
-        for i in 0..self.n {
-            for j in (i + 1)..self.n {
-                let interaction =
-                    self.c[solution[i]][solution[j]] + self.c[solution[j]][solution[i]];
-                // original: let distance = self.s[i..j + 1].iter().fold(0, |sum, v| sum + (s_sum - v));
-                // let distance = self.s[i..j + 1].iter().sum::<usize>();
-                let mut distance = 0;
-                for k in i..(j + 1) {
-                    distance += self.s[solution[k]];
-                }
-                // println!("i={i}, j={j}, interaction={interaction}, dist(norev)={distance}");
-                // original: f += interaction * distance as u64;
-                f += interaction * (s_sum - distance) as u64;
+        for (a, b, interaction) in self.c.edges() {
+            if a == b {
+                continue;
             }
+            let (i, j) = min_max(pos[a], pos[b]);
+            // original: let distance = self.s[i..j + 1].iter().fold(0, |sum, v| sum + (s_sum - v));
+            let distance = prefix[j + 1] - prefix[i];
+            // original: f += interaction * distance as u64;
+            f += interaction as u128 * (s_sum - distance) as u128;
         }
 
         return f;
     }
 
-    pub fn block_reordering_from(function: &Function) -> Option<Self> {
-        if function.bbs_branch_tree[0].is_empty() {
-            return None;
+    /// Upper bound of `eval`: every pair of items contributes at most its interaction times the
+    /// summed size of all the items. `None` if it doesn't fit in a `u128`.
+    pub fn max_fitness(&self) -> Option<u128> {
+        let s_sum = self
+            .s
+            .iter()
+            .try_fold(0u128, |sum, s| sum.checked_add(*s as u128))?;
+        self.c
+            .edges()
+            .filter(|(a, b, _)| a != b)
+            .try_fold(0u128, |sum, (_, _, w)| {
+                sum.checked_add((w as u128).checked_mul(s_sum)?)
+            })
+    }
+
+    /// Upper bound of `eval` over the solutions that start with `prefix`.
+    ///
+    /// `eval` is the summed weight of all the pairs times the summed size of all the items,
+    /// minus the size of each item times the weight of the pairs whose span contains it. An item
+    /// is spanned by the pairs that contain it and the pairs between the items placed before it
+    /// and the rest, so the items of the prefix are accounted exactly, and the rest of items are
+    /// at least spanned by the pairs that contain them.
+    pub fn bound_prefix(&self, prefix: &[usize]) -> u128 {
+        let s_sum: u128 = self.s.iter().map(|s| *s as u128).sum();
+        let degree =
+            |a: usize| -> u128 { self.c.neighbours(a).iter().map(|(_, w)| *w as u128).sum() };
+
+        let mut placed = vec![false; self.n];
+        // summed weight of the pairs between the placed items and the rest
+        let mut cross = 0;
+        let mut cost = 0;
+        for x in prefix {
+            let w_placed: u128 = self
+                .c
+                .neighbours(*x)
+                .iter()
+                .filter(|(b, _)| placed[*b])
+                .map(|(_, w)| *w as u128)
+                .sum();
+            cost += self.s[*x] as u128 * (degree(*x) + cross - w_placed);
+            cross = cross + degree(*x) - 2 * w_placed;
+            placed[*x] = true;
         }
 
-        let num_blocks = function.num_bbs;
-        let mut c = vec![vec![0; num_blocks]; num_blocks];
-
-        let mut no_md_branches = vec![];
-
-        for i in 0..num_blocks {
-            // if the basic block has some branch metadata
-            if let Some(Some(Metadata::Node(leafs))) = function.bb_terminator_md.get(i) {
-                let mut leafs_iter = leafs.iter();
-
-                // extract branch metadata
-                let branch_weights: Vec<u64> = match leafs_iter.next() {
-                    Some(Metadata::String(name)) if name == "branch_weights" => leafs_iter
-                        .map(|v| match v {
-                            Metadata::IntValue(w) => *w,
-                            _ => unreachable!(),
-                        })
-                        .collect(),
-                    _ => continue,
-                };
-
-                // set the weight of each branch
-                for (j, neighbor_idx) in function.bbs_branch_tree[i].iter().enumerate() {
-                    c[i][*neighbor_idx] = branch_weights[j];
+        let mut weight_sum = 0;
+        for (a, is_placed) in placed.iter().enumerate() {
+            weight_sum += degree(a);
+            if !is_placed {
+                cost += self.s[a] as u128 * degree(a);
+            }
+        }
+        // each pair is counted from both of its items
+        (s_sum * (weight_sum / 2)).saturating_sub(cost)
+    }
+
+    /// Returns the change in fitness that swapping the items at positions `i` and `j` of
+    /// `layout` would produce, without evaluating the whole solution.
+    pub fn delta_swap(&self, layout: &Layout, i: usize, j: usize) -> i128 {
+        if i == j {
+            return 0;
+        }
+        let (i, j) = min_max(i, j);
+        let (x, y) = (layout.order[i], layout.order[j]);
+        let ds = self.s[y] as i128 - self.s[x] as i128;
+
+        let prefix = |k: usize| layout.prefix[k] as i128;
+        // prefix sums once `x` and `y` are swapped
+        let new_prefix = |k: usize| prefix(k) + if i < k && k <= j { ds } else { 0 };
+        let distance = |a: usize, b: usize| {
+            let (lo, hi) = min_max(a, b);
+            prefix(hi + 1) - prefix(lo)
+        };
+        let new_distance = |a: usize, b: usize| {
+            let (lo, hi) = min_max(a, b);
+            new_prefix(hi + 1) - new_prefix(lo)
+        };
+
+        // change in the summed weighted distance of the pairs that contain `x` or `y`
+        let mut delta = 0;
+        // interaction of the pairs that contain `x` (or `y`) and span `i` (or `j`)
+        let (mut span_i, mut span_j) = (0, 0);
+
+        for &(r, w) in self.c.neighbours(x) {
+            let w = w as i128;
+            span_i += w;
+            if r == y {
+                continue;
+            }
+            let pr = layout.pos[r];
+            delta += w * (new_distance(j, pr) - distance(i, pr));
+            if pr > j {
+                span_j += w;
+            }
+        }
+
+        for &(r, w) in self.c.neighbours(y) {
+            let w = w as i128;
+            span_j += w;
+            if r == x {
+                continue;
+            }
+            let pr = layout.pos[r];
+            delta += w * (new_distance(i, pr) - distance(j, pr));
+            if pr < i {
+                span_i += w;
+            }
+        }
+
+        // the rest of the pairs only change their distance if they span one of the two
+        // positions, as the size of the item placed there changes
+        let rest_i = layout.cut[i] as i128 - span_i;
+        let rest_j = layout.cut[j] as i128 - span_j;
+        delta += ds * (rest_i - rest_j);
+
+        // the fitness grows as the distances shrink
+        -delta
+    }
+
+    /// Returns the change in fitness that moving the item at position `from` of `layout` to
+    /// position `to` would produce (see `Layout::insert`), without evaluating the whole solution.
+    pub fn delta_insert(&self, layout: &Layout, from: usize, to: usize) -> i128 {
+        if from == to {
+            return 0;
+        }
+        let z = layout.order[from];
+        let sz = self.s[z] as i128;
+
+        let prefix = |k: usize| layout.prefix[k] as i128;
+        let distance = |a: usize, b: usize| {
+            let (lo, hi) = min_max(a, b);
+            prefix(hi + 1) - prefix(lo)
+        };
+        // distance between `z` and the item in (old) position `pr` once `z` is moved
+        let new_distance = |pr: usize| {
+            if from < to {
+                if pr < from {
+                    prefix(to + 1) - prefix(pr)
+                } else if pr <= to {
+                    prefix(to + 1) - prefix(pr) + sz
+                } else {
+                    prefix(pr + 1) - prefix(to + 1) + sz
                 }
+            } else if pr < to {
+                prefix(to) - prefix(pr) + sz
+            } else if pr < from {
+                prefix(pr + 1) - prefix(to) + sz
             } else {
-                // there is no metadata for the branches
-                // take the branch weight as the sum of all branchs weights to this basic block
+                prefix(pr + 1) - prefix(to)
+            }
+        };
+
+        let mut delta = 0;
+        // total interaction of `z` and the part of it that crosses the new location of `z`
+        let (mut z_weight, mut z_crossing) = (0, 0);
+
+        for &(r, w) in self.c.neighbours(z) {
+            let w = w as i128;
+            z_weight += w;
+            let pr = layout.pos[r];
+            delta += w * (new_distance(pr) - distance(from, pr));
+            if (from < to && pr > to) || (from > to && pr < to) {
+                z_crossing += w;
+            }
+        }
+
+        // interaction of the item that is currently at `to` with the items on the side of `z`,
+        // these pairs end at `to` and thus do not cross the new location of `z`
+        let v = layout.order[to];
+        let mut v_side = 0;
+        for &(r, w) in self.c.neighbours(v) {
+            let w = w as i128;
+            let pr = layout.pos[r];
+            if (from < to && pr < to) || (from > to && pr > to) {
+                v_side += w;
+            }
+        }
 
-                for neighbor_idx in function.bbs_branch_tree[i].iter() {
-                    no_md_branches.push((i, *neighbor_idx))
+        // the rest of the pairs only change their distance if `z` enters or leaves their span
+        let rest_old = layout.cut[from] as i128 - z_weight;
+        let rest_new = layout.cut[to] as i128 - v_side - z_crossing;
+        delta += sz * (rest_new - rest_old);
+
+        -delta
+    }
+
+    /// Returns the change in fitness that moving the `len` items starting at position `start` of
+    /// `layout` so that they start at position `to` would produce (see `Layout::move_segment`),
+    /// without evaluating the whole solution.
+    pub fn delta_move_segment(&self, layout: &Layout, start: usize, len: usize, to: usize) -> i128 {
+        if start == to || len == 0 {
+            return 0;
+        }
+        // the segment swaps places with the block of items it jumps over, `lo..mid` is the block
+        // on the left and `mid..hi` the one on the right
+        let (lo, mid, hi) = if start < to {
+            (start, start + len, to + len)
+        } else {
+            (to, start, start + len)
+        };
+        let segment_is_left = start < to;
+
+        let prefix = |k: usize| layout.prefix[k] as i128;
+        let (left_size, right_size) = (prefix(mid) - prefix(lo), prefix(hi) - prefix(mid));
+        // change in the distance between the items at positions `p` (left) and `q` (right)
+        let across = |p: usize, q: usize| {
+            let old = prefix(q + 1) - prefix(p);
+            let new = (prefix(hi) - prefix(q)) + (prefix(p + 1) - prefix(lo));
+            new - old
+        };
+
+        let mut delta = 0;
+        // interaction of the segment with the items before and after both blocks
+        let (mut w_before, mut w_after) = (0, 0);
+        for p in start..start + len {
+            for &(r, w) in self.c.neighbours(layout.order[p]) {
+                let w = w as i128;
+                let pr = layout.pos[r];
+                if pr < lo {
+                    w_before += w;
+                } else if pr >= hi {
+                    w_after += w;
+                } else if segment_is_left && pr >= mid {
+                    delta += w * across(p, pr);
+                } else if !segment_is_left && pr < mid {
+                    delta += w * across(pr, p);
+                }
+            }
+        }
+
+        // the pairs between a block and the items outside of both blocks change their distance
+        // by the size of the other block. The ones of the block that isn't the segment are
+        // derived from the pairs that cross the borders of the blocks, the rest of them cancel
+        // out
+        let cross_lo = self.cross(layout, lo);
+        let cross_hi = self.cross(layout, hi);
+        if segment_is_left {
+            delta += right_size * (w_before - w_after);
+            delta += left_size * (cross_hi - w_after - cross_lo + w_before);
+        } else {
+            delta += right_size * (cross_lo - w_before - cross_hi + w_after);
+            delta += left_size * (w_after - w_before);
+        }
+
+        -delta
+    }
+
+    /// Returns the change in fitness that reversing the items at positions `i..=j` of `layout`
+    /// would produce, without evaluating the whole solution.
+    pub fn delta_reverse(&self, layout: &Layout, i: usize, j: usize) -> i128 {
+        let (i, j) = min_max(i, j);
+        let prefix = |k: usize| layout.prefix[k] as i128;
+
+        // only the pairs with a single item in the segment change their distance: the item at `p`
+        // gets closer to the items before the segment by as much as it gets away from the ones
+        // after it
+        let mut delta = 0;
+        for p in i..=j {
+            let shift = prefix(i) + prefix(j + 1) - prefix(p) - prefix(p + 1);
+            for &(r, w) in self.c.neighbours(layout.order[p]) {
+                let pr = layout.pos[r];
+                if pr < i {
+                    delta += w as i128 * shift;
+                } else if pr > j {
+                    delta -= w as i128 * shift;
                 }
             }
         }
 
-        // for each missing branch
-        for (br_from, br_to) in no_md_branches.clone() {
-            c[br_from][br_to] = Self::fill_missing_branch_weights(
-                &c,
-                br_from,
-                &mut no_md_branches,
-                &function.bbs_branch_tree,
-                0,
-                num_blocks,
-            );
+        -delta
+    }
+
+    /// Summed interaction of the pairs with an item placed before position `k` and the other at
+    /// `k` or after it.
+    pub(super) fn cross(&self, layout: &Layout, k: usize) -> i128 {
+        if k == 0 || k >= layout.order.len() {
+            return 0;
         }
+        // the pairs that span `k` but start at it don't cross
+        let starting: i128 = self
+            .c
+            .neighbours(layout.order[k])
+            .iter()
+            .filter(|(r, _)| layout.pos[*r] > k)
+            .map(|(_, w)| *w as i128)
+            .sum();
+        layout.cut[k] as i128 - starting
+    }
+
+    pub fn block_reordering_from(function: &Function) -> Option<Self> {
+        Self::block_reordering_with(function, &function.infer_branch_weights().weights)
+    }
+
+    /// Builds the block reordering problem of `function` given the `(from, to)` weight of its
+    /// branches, as returned by `Function::infer_branch_weights`.
+    pub fn block_reordering_with(
+        function: &Function,
+        weights: &HashMap<(usize, usize), u64>,
+    ) -> Option<Self> {
+        if function.bbs_branch_tree[0].is_empty() {
+            return None;
+        }
+
+        let num_blocks = function.num_bbs;
 
-        // TODO: Optimize
-        // remove the data referring to the entry basic block, as
-        // it dosn't take part in the optimization problem
-        let s = function.bbs_num_instrs[1..num_blocks].to_vec();
-        c.remove(0);
-        c.iter_mut().for_each(|row| {
-            let _ = row.remove(0);
-        });
+        let s = function.bbs_sizes().to_vec();
+        let c = Interactions::from_edges(
+            num_blocks,
+            weights.iter().map(|((from, to), w)| (*from, *to, *w)),
+        );
+
+        // the entry basic block must stay in the first position
+        let constraints = Constraints::new(num_blocks, vec![(0, 0)], vec![], vec![]).unwrap();
 
         Some(CoProblem {
             c,
             s,
-            n: num_blocks - 1,
+            n: num_blocks,
+            constraints,
         })
     }
 
-    fn fill_missing_branch_weights(
-        c: &Vec<Vec<u64>>,
-        br_from: usize,
-        missing_branches: &mut Vec<(usize, usize)>,
-        branch_tree: &Vec<Vec<usize>>,
-        recursion_depth: usize,
-        max_recursion: usize,
-    ) -> u64 {
-        if recursion_depth > max_recursion {
-            return 0;
+    /// Returns `true` if the sum of all elements of the `C` matrix of the `CoProblem` is zero.
+    pub fn is_zeros(&self) -> bool {
+        self.c.num_edges() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::co::neighborhood::Neighborhood;
+    use crate::co::Interaction;
+
+    /// A problem of `n` items with random sizes and interactions. If `constrained`, the first item
+    /// is pinned and there's a random adjacency and precedence.
+    fn random_problem(n: usize, constrained: bool, rng: &mut StdRng) -> CoProblem {
+        let s = (0..n).map(|_| rng.gen_range(0..10)).collect();
+        let edges = (0..2 * n)
+            .map(|_| {
+                (
+                    rng.gen_range(0..n),
+                    rng.gen_range(0..n),
+                    rng.gen_range(0..100),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut constraints = Constraints::default();
+        if constrained && n > 4 {
+            constraints = loop {
+                let mut items = (1..n).collect::<Vec<usize>>();
+                items.shuffle(rng);
+                let pinned = vec![(0, 0)];
+                let adjacent = vec![(items[0], items[1])];
+                let precedence = vec![(items[2], items[3])];
+                if let Ok(c) = Constraints::new(n, pinned, precedence, adjacent) {
+                    break c;
+                }
+            };
         }
 
-        // get the index of each basic block that calls `br_from`
-        let parents = branch_tree
-            .iter()
-            .enumerate()
-            .filter(|(_, v)| v.contains(&br_from))
-            .map(|(i, _)| i)
-            .collect::<Vec<usize>>();
-
-        let mut sum: u64 = 0;
-
-        for parent in parents {
-            if missing_branches.contains(&(parent, br_from)) {
-                sum += Self::fill_missing_branch_weights(
-                    c,
-                    parent,
-                    missing_branches,
-                    branch_tree,
-                    recursion_depth + 1,
-                    max_recursion,
-                );
-
-                // note: the missing branch could be removed in the recursive call above
-                // that's why there's an `if let` here
-                if let Some(idx) = missing_branches
-                    .iter()
-                    .position(|v| *v == (parent, br_from))
-                {
-                    missing_branches.remove(idx);
+        CoProblem {
+            c: Interactions::from_edges(n, edges),
+            s,
+            n,
+            constraints,
+        }
+    }
+
+    /// Checks the incremental delta and feasibility of every move of `neighborhood` from random
+    /// solutions, and the layout the move leaves, against the ones of the whole neighbor.
+    fn check_moves(neighborhood: Neighborhood) {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for n in [2, 3, 5, 8] {
+            for constrained in [false, true] {
+                for _ in 0..5 {
+                    let problem = random_problem(n, constrained, &mut rng);
+                    let mut order = (0..n).collect::<Vec<usize>>();
+                    order.shuffle(&mut rng);
+                    let order = problem.constraints.repair(&order);
+                    let layout = Layout::new(&problem, &Interaction, order);
+                    let fitness = problem.eval(layout.order()) as i128;
+
+                    for mv in neighborhood.moves(n) {
+                        let neighbor = mv.neighbor(layout.order());
+                        assert_eq!(
+                            mv.delta(&problem, &Interaction, &layout),
+                            problem.eval(&neighbor) as i128 - fitness,
+                            "delta of {mv:?} from {:?}",
+                            layout.order()
+                        );
+                        assert_eq!(
+                            mv.is_allowed(&problem, &layout),
+                            problem.constraints.is_feasible(&neighbor),
+                            "feasibility of {mv:?} from {:?}",
+                            layout.order()
+                        );
+
+                        let mut moved = layout.clone();
+                        mv.apply(&problem, &Interaction, &mut moved);
+                        let expected = Layout::new(&problem, &Interaction, neighbor);
+                        assert_eq!(moved.order, expected.order);
+                        assert_eq!(moved.pos, expected.pos);
+                        assert_eq!(moved.prefix, expected.prefix);
+                        assert_eq!(moved.cut, expected.cut, "cut after {mv:?}");
+                        assert_eq!(moved.fitness, expected.fitness);
+                    }
                 }
-            } else {
-                sum += c[parent][br_from];
             }
         }
+    }
 
-        return sum;
+    #[test]
+    fn delta_move_segment_matches_eval() {
+        check_moves(Neighborhood::SegmentMove { max_len: 4 });
     }
 
-    /// Returns `true` if the sum of all elements of the `C` matrix of the `CoProblem` is zero.
-    pub fn is_zeros(&self) -> bool {
-        self.c
-            .iter()
-            .find(|row| row.iter().sum::<u64>() != 0)
-            .is_none()
+    #[test]
+    fn delta_reverse_matches_eval() {
+        check_moves(Neighborhood::Reversal);
+    }
+
+    #[test]
+    fn delta_swap_matches_eval() {
+        check_moves(Neighborhood::Swap);
+    }
+
+    #[test]
+    fn delta_insert_matches_eval() {
+        check_moves(Neighborhood::Insert);
     }
 }
@@ -1,108 +1,107 @@
 use rand::prelude::*;
 use rand::seq::SliceRandom;
 
-use std::time::Instant;
-
 #[cfg(feature = "log")]
 use crate::log;
 
-use super::CoProblem;
-use crate::MAX_OPT_MILLIS;
-
-pub fn run(
-    problem: &CoProblem,
-    temp_init: f64,
-    temp_update: f64,
-    temp_end: f64,
-    temp_update_iters: usize,
-) -> (Vec<usize>, u64) {
-    let time = Instant::now();
-    let mut temp = temp_init;
-
-    let mut rng = rand::thread_rng();
-    let mut best_solution = (0..problem.n).collect::<Vec<usize>>();
-    best_solution.shuffle(&mut rng);
-
-    let mut best_solution_f = problem.eval(&best_solution);
-
-    let mut solution = best_solution.clone();
-    let mut solution_f = problem.eval(&solution);
-
-    #[cfg(feature = "log")]
-    let mut evals = 2;
-
-    loop {
-        for _it in 0..temp_update_iters {
-            // get random neighbor
-            let mut neighbor = solution.clone(); // TODO: Optimize
-            let (_i, _j) = random_swap(&mut neighbor);
-
-            let neighbor_f = problem.eval(&neighbor);
-
-            #[cfg(feature = "log")]
-            {
-                evals += 1;
-                log::log("time", time.elapsed().as_millis());
-                log::log("evaluation", evals);
-                log::log("best fitness", best_solution_f);
-            }
+use super::neighborhood::Neighborhood;
+use super::solver::{Run, Solver};
+use super::{CoProblem, Layout, Objective};
+
+/// Simulated annealing with a geometric cooling schedule.
+pub struct SimulatedAnnealing {
+    /// Neighborhood the random neighbors are drawn from
+    pub neighborhood: Neighborhood,
+    pub temp_init: f64,
+    /// Factor the temperature is multiplied by every `temp_update_iters` iterations
+    pub temp_update: f64,
+    /// The temperature isn't lowered below this one
+    pub temp_end: f64,
+    pub temp_update_iters: usize,
+}
 
-            let energy = (neighbor_f as i64 - solution_f as i64) as f64;
+impl Default for SimulatedAnnealing {
+    fn default() -> Self {
+        SimulatedAnnealing {
+            neighborhood: Neighborhood::Swap,
+            temp_init: 1000000.0,
+            temp_update: 0.95,
+            temp_end: 10000.0,
+            temp_update_iters: 100,
+        }
+    }
+}
 
-            if energy > 0. {
-                solution_f = neighbor_f;
-                solution = neighbor.clone();
+impl Solver for SimulatedAnnealing {
+    fn name(&self) -> &'static str {
+        "SA"
+    }
 
-                if solution_f > best_solution_f {
-                    best_solution_f = solution_f;
-                    best_solution = solution.clone();
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut temp = self.temp_init;
+
+        let mut initial = (0..problem.n).collect::<Vec<usize>>();
+        initial.shuffle(&mut run.rng);
+        let initial = problem.constraints.repair(&initial);
+
+        let mut solution = Layout::new(problem, objective, initial);
+        let mut best_solution = solution.clone();
+        run.evaluated(1, best_solution.fitness());
+
+        // a single item has no neighbors, and the constraints may leave no feasible one, in
+        // which case the random moves would be drawn until the time limit
+        let stuck = problem.n < 2 || !self.neighborhood.has_allowed_move(problem, &solution);
+        while !stuck && !run.is_over() {
+            for _it in 0..self.temp_update_iters {
+                // get random neighbor
+                let mv = self
+                    .neighborhood
+                    .random_move(solution.order().len(), &mut run.rng);
+                // only feasible neighbors are considered
+                if !mv.is_allowed(problem, &solution) {
+                    continue;
                 }
-            } else {
-                let r = rand::random::<f64>();
-                if r < (energy / temp).exp() {
-                    solution_f = neighbor_f;
-                    solution = neighbor.clone();
+                let energy = mv.delta(problem, objective, &solution) as f64;
+
+                if energy > 0. {
+                    mv.apply(problem, objective, &mut solution);
+
+                    if solution.fitness() > best_solution.fitness() {
+                        best_solution = solution.clone();
+                    }
+                } else {
+                    let r = run.rng.gen::<f64>();
+                    if r < (energy / temp).exp() {
+                        mv.apply(problem, objective, &mut solution);
+                    }
                 }
-            }
+                run.evaluated(1, best_solution.fitness());
 
-            if MAX_OPT_MILLIS <= time.elapsed().as_millis() {
-                break;
+                if run.is_over() {
+                    break;
+                }
+            }
+            // update temperature
+            if temp > self.temp_end {
+                temp *= self.temp_update;
             }
         }
-        // update temperature
-        if temp > temp_end {
-            temp *= temp_update;
-        }
-        if MAX_OPT_MILLIS <= time.elapsed().as_millis() {
-            break;
-        }
-    }
 
-    #[cfg(feature = "log")]
-    {
-        log::set_attr("algorithm", "SA");
-        log::set_attr("temp init", temp_init);
-        log::set_attr("temp update", temp_update);
-        log::set_attr("temp end", temp_end);
-        log::set_attr("temp update iters", temp_update_iters);
-        log::write();
-    }
-
-    (best_solution, best_solution_f)
-}
-
-pub fn random_swap(s: &mut [usize]) -> (usize, usize) {
-    let mut rng = rand::thread_rng();
-    let i = rng.gen_range(0..s.len());
-    let mut j = rng.gen_range(0..s.len());
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("neighborhood", format!("{:?}", self.neighborhood));
+            log::set_attr("temp init", self.temp_init);
+            log::set_attr("temp update", self.temp_update);
+            log::set_attr("temp end", self.temp_end);
+            log::set_attr("temp update iters", self.temp_update_iters);
+        }
 
-    while i == j {
-        j = rng.gen_range(0..s.len());
+        let best_f = best_solution.fitness();
+        (best_solution.into_order(), best_f)
     }
-
-    let v = s[i];
-    s[i] = s[j];
-    s[j] = v;
-
-    (i, j)
 }
@@ -0,0 +1,479 @@
+use rand::prelude::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::{
+    aco::Aco, constructive::Constructive, eda::Eda, ga::Ga, grasp::Grasp,
+    local_search::LocalSearch, sa::SimulatedAnnealing, tabu::TabuSearch, vns::Vns, CoProblem,
+    Objective,
+};
+use crate::MAX_OPT_MILLIS;
+
+/// Limits of a run of a solver, which stops as soon as any of them is reached.
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    /// Wall time, in milliseconds
+    pub millis: u128,
+    /// Number of evaluations, of solutions or of moves
+    pub evaluations: Option<usize>,
+    /// Fitness that is good enough to stop
+    pub target: Option<u128>,
+    /// Instant to stop at, whatever the time left, e.g. the end of a time shared by many runs
+    pub deadline: Option<Instant>,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            millis: MAX_OPT_MILLIS,
+            evaluations: None,
+            target: None,
+            deadline: None,
+        }
+    }
+}
+
+/// Time held back from a global budget shared by the runs of many problems, which is given in
+/// slices to the runs that are still improving when their own share of the budget is over.
+///
+/// Deciding which runs go on works like a bandit: the reward of a run is the fitness it gained
+/// per millisecond over its last slice of time, relative to the scale of the fitness of its
+/// problem, and a run only gets one more slice while its reward is positive and at least the
+/// mean reward of all the runs that asked for one so far.
+/// The time that a run doesn't use is given back to the pool.
+pub struct TimePool {
+    /// Milliseconds of each extension
+    slice: u128,
+    state: Mutex<PoolState>,
+}
+
+struct PoolState {
+    /// Milliseconds left in the pool
+    millis: u128,
+    reward_sum: f64,
+    requests: usize,
+}
+
+impl TimePool {
+    pub fn new(millis: u128, slice: u128) -> Self {
+        TimePool {
+            slice: slice.max(1),
+            state: Mutex::new(PoolState {
+                millis,
+                reward_sum: 0.0,
+                requests: 0,
+            }),
+        }
+    }
+
+    /// Milliseconds given to a run whose share is over and whose reward is `reward` (0 if it
+    /// must stop).
+    fn extend(&self, reward: f64) -> u128 {
+        let mut state = self.state.lock().unwrap();
+        let mean = if state.requests > 0 {
+            state.reward_sum / state.requests as f64
+        } else {
+            0.0
+        };
+        state.reward_sum += reward;
+        state.requests += 1;
+
+        if reward > 0.0 && reward >= mean {
+            let millis = self.slice.min(state.millis);
+            state.millis -= millis;
+            millis
+        } else {
+            0
+        }
+    }
+
+    /// Takes up to `millis` milliseconds out of the pool, and returns how many it took.
+    pub fn take(&self, millis: u128) -> u128 {
+        let mut state = self.state.lock().unwrap();
+        let millis = millis.min(state.millis);
+        state.millis -= millis;
+        millis
+    }
+
+    fn give_back(&self, millis: u128) {
+        self.state.lock().unwrap().millis += millis;
+    }
+}
+
+/// State of a run, reported to its observer each time the best fitness improves.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub evaluations: usize,
+    pub millis: u128,
+    pub best_fitness: u128,
+}
+
+/// Outcome of a run of a solver.
+#[derive(Debug, Clone)]
+pub struct SolverResult {
+    pub solution: Vec<usize>,
+    pub fitness: u128,
+    pub evaluations: usize,
+    pub millis: u128,
+}
+
+/// Callback that follows the progress of a run.
+pub type Observer = Box<dyn FnMut(&Progress) + Send>;
+
+/// State shared by the runs split from the same one.
+struct Split {
+    /// Raised when any of the runs reaches the target fitness
+    stop: AtomicBool,
+    /// Time limit of all the runs, which the first of them whose time is over extends with the
+    /// pool for all of them, and whether the pool refused to extend it
+    limit: Mutex<(u128, bool)>,
+}
+
+/// Budget, random number generator and counters of a single run of an algorithm.
+pub struct Run {
+    /// Every random decision of the algorithm must be drawn from here, so that runs with the
+    /// same seed are repeatable
+    pub rng: StdRng,
+    budget: Budget,
+    time: Instant,
+    evaluations: usize,
+    best_fitness: Option<u128>,
+    observer: Option<Observer>,
+    /// State shared with the other runs split from the same one, if it is one of them
+    split: Option<Arc<Split>>,
+    /// Pool the run asks for more time when its time is over, if any
+    pool: Option<Arc<TimePool>>,
+    /// Scale of the fitness of the problem, which the reward for the pool is relative to
+    fitness_scale: f64,
+    /// Time and fitness of each improvement of the best fitness, to compute the reward of the
+    /// run for the pool
+    improvements: Vec<(u128, u128)>,
+}
+
+impl Run {
+    pub fn new(budget: Budget, seed: u64) -> Self {
+        Run {
+            rng: StdRng::seed_from_u64(seed),
+            budget,
+            time: Instant::now(),
+            evaluations: 0,
+            best_fitness: None,
+            observer: None,
+            split: None,
+            pool: None,
+            fitness_scale: 1.0,
+            improvements: vec![],
+        }
+    }
+
+    /// Calls `observer` each time the best fitness of the run improves.
+    pub fn with_observer(mut self, observer: impl FnMut(&Progress) + Send + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Asks `pool` for more time each time the time of the run is over, and gives back the time
+    /// it doesn't use when it finishes. The improvements of the run are measured relative to
+    /// `max_fitness`, so that the runs of problems with fitnesses of different scales compare.
+    pub fn with_pool(mut self, pool: Arc<TimePool>, max_fitness: u128) -> Self {
+        self.pool = Some(pool);
+        self.fitness_scale = max_fitness.max(1) as f64;
+        self
+    }
+
+    /// Counts `count` evaluations, after which the best fitness found is `best_fitness`.
+    pub fn evaluated(&mut self, count: usize, best_fitness: u128) {
+        self.evaluations += count;
+
+        #[cfg(feature = "log")]
+        {
+            log::log("time", self.millis());
+            log::log("evaluation", self.evaluations);
+            log::log("best fitness", best_fitness);
+        }
+
+        if Some(best_fitness) > self.best_fitness {
+            self.best_fitness = Some(best_fitness);
+            let progress = Progress {
+                evaluations: self.evaluations,
+                millis: self.millis(),
+                best_fitness,
+            };
+            if let Some(observer) = &mut self.observer {
+                observer(&progress);
+            }
+            if self.pool.is_some() {
+                self.improvements.push((progress.millis, best_fitness));
+            }
+
+            if let Some(split) = &self.split {
+                if matches!(self.budget.target, Some(t) if t <= best_fitness) {
+                    split.stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Splits the rest of the budget into `count` runs, to be run in parallel, with seeds drawn
+    /// from this run. Each of them gets the time left and an equal part of the evaluations
+    /// left, and all of them stop as soon as one reaches the target fitness. They ask the pool
+    /// of this run for more time together, and share its observer, which only sees the
+    /// improvements of the best fitness of all of them.
+    pub fn split(&mut self, count: usize) -> Vec<Run> {
+        let millis = self.budget.millis.saturating_sub(self.millis());
+        let left = self
+            .budget
+            .evaluations
+            .map(|e| e.saturating_sub(self.evaluations));
+        let split = Arc::new(Split {
+            stop: AtomicBool::new(false),
+            limit: Mutex::new((millis, false)),
+        });
+        let observer = self
+            .observer
+            .take()
+            .map(|observer| Arc::new(Mutex::new((self.best_fitness, observer))));
+        self.observer = observer.as_ref().map(shared_observer);
+
+        (0..count)
+            .map(|k| {
+                let budget = Budget {
+                    millis,
+                    evaluations: left.map(|l| l / count + usize::from(k < l % count)),
+                    target: self.budget.target,
+                    deadline: self.budget.deadline,
+                };
+                let mut run = Run::new(budget, self.rng.gen());
+                run.split = Some(split.clone());
+                run.pool = self.pool.clone();
+                run.fitness_scale = self.fitness_scale;
+                run.observer = observer.as_ref().map(shared_observer);
+                run
+            })
+            .collect()
+    }
+
+    /// Counts the evaluations of the runs split from this one, which found `best_fitness`, and
+    /// takes the time limit they were extended to.
+    pub fn join(&mut self, runs: &[Run], best_fitness: u128) {
+        let evaluations = runs.iter().map(|r| r.evaluations).sum();
+        self.evaluated(evaluations, best_fitness);
+
+        if let Some(split) = runs.first().and_then(|r| r.split.as_ref()) {
+            let (millis, refused) = *split.limit.lock().unwrap();
+            // the split runs count the time from when they were split
+            let start = self.millis().saturating_sub(runs[0].millis());
+            self.budget.millis = self.budget.millis.max(start + millis);
+            if refused {
+                self.pool = None;
+            }
+        }
+    }
+
+    /// Counts `count` evaluations of algorithms without a single fitness to report (e.g.
+    /// multi-objective ones).
+    pub fn count(&mut self, count: usize) {
+        self.evaluations += count;
+    }
+
+    /// Whether any of the limits of the budget has been reached. When the time is over, the
+    /// run first asks its pool for more, if it has one, unless the deadline has passed.
+    pub fn is_over(&mut self) -> bool {
+        if matches!(self.budget.evaluations, Some(e) if e <= self.evaluations)
+            || matches!((self.budget.target, self.best_fitness), (Some(t), Some(f)) if t <= f)
+            || matches!(&self.split, Some(split) if split.stop.load(Ordering::Relaxed))
+            || matches!(self.budget.deadline, Some(d) if d <= Instant::now())
+        {
+            return true;
+        }
+
+        if self.budget.millis <= self.millis() {
+            self.extend();
+        }
+        self.budget.millis <= self.millis()
+    }
+
+    /// Asks the pool for one more slice of time. Once refused, the run doesn't ask again. The
+    /// runs split from the same one ask once for all of them.
+    fn extend(&mut self) {
+        let pool = match &self.pool {
+            Some(pool) => pool.clone(),
+            None => return,
+        };
+        let split = self.split.clone();
+        let mut limit = split.as_ref().map(|split| split.limit.lock().unwrap());
+        if let Some((millis, refused)) = limit.as_deref() {
+            if *refused {
+                self.pool = None;
+                return;
+            }
+            if *millis > self.budget.millis {
+                self.budget.millis = *millis;
+                return;
+            }
+        }
+
+        let now = self.millis();
+        let since = now.saturating_sub(pool.slice);
+        let best = self.best_fitness.unwrap_or(0);
+        // best fitness at the start of the last slice
+        let before = self
+            .improvements
+            .iter()
+            .rev()
+            .find(|(millis, _)| *millis <= since)
+            .or_else(|| self.improvements.first())
+            .map_or(best, |(_, f)| *f);
+        let reward = (best - before) as f64 / self.fitness_scale / (now - since).max(1) as f64;
+
+        match pool.extend(reward) {
+            0 => self.pool = None,
+            millis => self.budget.millis = now + millis,
+        }
+        if let Some(limit) = &mut limit {
+            **limit = (self.budget.millis, self.pool.is_none());
+        }
+    }
+
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+
+    /// Milliseconds since the run started.
+    pub fn millis(&self) -> u128 {
+        self.time.elapsed().as_millis()
+    }
+
+    pub fn finish(self, solution: Vec<usize>, fitness: u128) -> SolverResult {
+        let millis = self.millis();
+        if let Some(pool) = &self.pool {
+            pool.give_back(self.budget.millis.saturating_sub(millis));
+        }
+
+        SolverResult {
+            solution,
+            fitness,
+            evaluations: self.evaluations,
+            millis,
+        }
+    }
+}
+
+/// Observer that forwards to the one shared by the runs split from the same one (see
+/// `Run::split`) the improvements of the best fitness of all of them.
+fn shared_observer(shared: &Arc<Mutex<(Option<u128>, Observer)>>) -> Observer {
+    let shared = shared.clone();
+    Box::new(move |progress: &Progress| {
+        let (best_fitness, observer) = &mut *shared.lock().unwrap();
+        if Some(progress.best_fitness) > *best_fitness {
+            *best_fitness = Some(progress.best_fitness);
+            observer(progress);
+        }
+    })
+}
+
+/// Seed of the random stream of the problem named `name` in a run with the given seed. Each
+/// problem gets the same stream no matter the order the problems are solved in.
+pub fn stream_seed(seed: u64, name: &str) -> u64 {
+    // FNV-1a, which unlike the hashers of `std` is stable across platforms and Rust versions
+    seed.to_le_bytes()
+        .iter()
+        .chain(name.as_bytes())
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// A single-objective algorithm.
+pub trait Solver: Sync {
+    /// Name of the solver, as selected in the optimizer and written to the logs
+    fn name(&self) -> &'static str;
+
+    /// Searches the best solution it can until the budget of `run` is over. Returns it with
+    /// its fitness.
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128);
+
+    /// Runs `search`, writing its log.
+    fn solve(&self, problem: &CoProblem, objective: &dyn Objective, mut run: Run) -> SolverResult {
+        let (solution, fitness) = self.search(problem, objective, &mut run);
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("algorithm", self.name());
+            log::write();
+        }
+
+        run.finish(solution, fitness)
+    }
+}
+
+/// Every single-objective solver, with its default parameters.
+pub fn registry() -> Vec<Box<dyn Solver>> {
+    vec![
+        Box::new(Constructive::default()),
+        Box::new(LocalSearch),
+        Box::new(SimulatedAnnealing::default()),
+        Box::new(Eda::default()),
+        Box::new(TabuSearch::default()),
+        Box::new(Vns::default()),
+        Box::new(Ga::default()),
+        Box::new(Aco::default()),
+        Box::new(Grasp::default()),
+    ]
+}
+
+/// The solver of the registry with the given name.
+pub fn by_name(name: &str) -> Option<Box<dyn Solver>> {
+    registry().into_iter().find(|s| s.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_runs_share_the_observer() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let observed = seen.clone();
+        let mut run = Run::new(Budget::default(), 0)
+            .with_observer(move |p: &Progress| observed.lock().unwrap().push(p.best_fitness));
+        run.evaluated(1, 5);
+
+        let mut runs = run.split(2);
+        runs[0].evaluated(1, 10);
+        runs[1].evaluated(1, 7);
+        runs[1].evaluated(1, 20);
+        run.join(&runs, 20);
+
+        assert_eq!(*seen.lock().unwrap(), vec![5, 10, 20]);
+        assert_eq!(run.evaluations(), 4);
+    }
+
+    #[test]
+    fn split_runs_ask_the_pool_together() {
+        let pool = Arc::new(TimePool::new(100, 10));
+        let budget = Budget {
+            millis: 0,
+            ..Default::default()
+        };
+        let mut run = Run::new(budget, 0).with_pool(pool.clone(), 1);
+
+        // without improvements the pool refuses to extend the first run, and then all of them
+        let mut runs = run.split(3);
+        assert!(runs.iter_mut().all(|run| run.is_over()));
+        assert_eq!(pool.state.lock().unwrap().requests, 1);
+
+        run.join(&runs, 0);
+        assert!(run.pool.is_none());
+    }
+}
@@ -0,0 +1,255 @@
+use rand::prelude::*;
+
+use std::collections::BTreeMap;
+
+use super::{CoProblem, Constraints, Interactions};
+
+/// Parameters of the random control flow graphs built by `random_problem`.
+///
+/// The CFGs are structured: the body of a function is a sequence of statements, each of them a
+/// plain block, a loop, an if-then(-else) diamond, a switch or a check with a cold error path.
+/// The bodies of loops, diamonds and switches are sequences of statements too, nested up to
+/// `max_depth` levels.
+#[derive(Debug, Clone)]
+pub struct SyntheticConfig {
+    /// Probability of each kind of statement, a plain block otherwise
+    pub loop_prob: f64,
+    pub diamond_prob: f64,
+    pub switch_prob: f64,
+    pub error_prob: f64,
+    /// Maximum nesting level of loops, diamonds and switches
+    pub max_depth: usize,
+    /// Largest number of cases of a switch
+    pub max_switch_cases: usize,
+    /// Mean number of iterations of a loop each time it is entered
+    pub mean_trip_count: f64,
+    /// Probability that a two-way branch is biased, taking one of its sides at least 90% of the
+    /// times (otherwise the probability of each side is uniform)
+    pub bias_prob: f64,
+    /// Mean fraction of the times a check takes its error path
+    pub mean_error_rate: f64,
+    /// Mean size of a block (e.g. in instructions)
+    pub mean_block_size: f64,
+    /// Mean number of times a function is entered
+    pub mean_entry_count: f64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        SyntheticConfig {
+            loop_prob: 0.15,
+            diamond_prob: 0.3,
+            switch_prob: 0.05,
+            error_prob: 0.15,
+            max_depth: 3,
+            max_switch_cases: 8,
+            mean_trip_count: 10.0,
+            bias_prob: 0.8,
+            mean_error_rate: 0.001,
+            mean_block_size: 8.0,
+            mean_entry_count: 1000.0,
+        }
+    }
+}
+
+/// Builds the block reordering problem of a random function with about `num_blocks` blocks (a
+/// few more if the last statement needs them). As in the problems of real functions, the entry
+/// block is item `0` and is pinned to the first position, and the blocks are numbered in the
+/// order of the source code.
+pub fn random_problem(
+    config: &SyntheticConfig,
+    num_blocks: usize,
+    rng: &mut impl Rng,
+) -> CoProblem {
+    let mut builder = Builder {
+        config,
+        rng,
+        sizes: vec![],
+        weights: BTreeMap::new(),
+        // the entry and the return blocks are always there
+        budget: num_blocks.saturating_sub(2),
+    };
+
+    let entry = builder.block();
+    let count = exponential(builder.rng, builder.config.mean_entry_count).round() as u64 + 1;
+
+    let (mut last, mut count) = (entry, count);
+    while builder.budget > 0 {
+        (last, count) = builder.statement(last, count, 0);
+    }
+    let ret = builder.block();
+    builder.edge(last, ret, count);
+
+    let n = builder.sizes.len();
+    CoProblem {
+        c: Interactions::from_edges(
+            n,
+            builder
+                .weights
+                .into_iter()
+                .filter(|(_, w)| *w > 0)
+                .map(|((a, b), w)| (a, b, w)),
+        ),
+        s: builder.sizes,
+        n,
+        constraints: Constraints::new(n, vec![(0, 0)], vec![], vec![]).unwrap(),
+    }
+}
+
+struct Builder<'a, R: Rng> {
+    config: &'a SyntheticConfig,
+    rng: &'a mut R,
+    sizes: Vec<usize>,
+    /// Weight of each `(from, to)` branch
+    weights: BTreeMap<(usize, usize), u64>,
+    /// Number of blocks left to generate
+    budget: usize,
+}
+
+impl<R: Rng> Builder<'_, R> {
+    fn block(&mut self) -> usize {
+        let size = exponential(self.rng, self.config.mean_block_size - 1.0).round() as usize + 1;
+        self.sizes.push(size);
+        self.budget = self.budget.saturating_sub(1);
+        self.sizes.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, count: u64) {
+        *self.weights.entry((from, to)).or_insert(0) += count;
+    }
+
+    /// Generates a statement that is entered `count` times from block `from`. Returns its last
+    /// block and the number of times it is left through it.
+    fn statement(&mut self, from: usize, count: u64, depth: usize) -> (usize, u64) {
+        let config = self.config;
+        let nested = depth < config.max_depth;
+        let mut r = self.rng.gen::<f64>();
+        let mut pick = |prob: f64, blocks: usize| {
+            r -= prob;
+            r < 0.0 && self.budget >= blocks
+        };
+
+        if nested && pick(config.loop_prob, 3) {
+            self.loop_statement(from, count, depth)
+        } else if nested && pick(config.diamond_prob, 4) {
+            self.diamond(from, count, depth)
+        } else if nested && pick(config.switch_prob, 2 + config.max_switch_cases) {
+            self.switch(from, count, depth)
+        } else if pick(config.error_prob, 3) {
+            self.error_check(from, count)
+        } else {
+            let block = self.block();
+            self.edge(from, block, count);
+            (block, count)
+        }
+    }
+
+    /// A sequence of statements, one or more of them.
+    fn region(&mut self, from: usize, count: u64, depth: usize) -> (usize, u64) {
+        let (mut last, mut count) = self.statement(from, count, depth);
+        while self.budget > 0 && self.rng.gen_bool(0.5) {
+            (last, count) = self.statement(last, count, depth);
+        }
+        (last, count)
+    }
+
+    /// A loop header, a body that jumps back to the header, and the exit of the loop.
+    fn loop_statement(&mut self, from: usize, count: u64, depth: usize) -> (usize, u64) {
+        let header = self.block();
+        self.edge(from, header, count);
+
+        let trips = exponential(self.rng, self.config.mean_trip_count);
+        let iterations = (count as f64 * trips).round() as u64;
+        let (latch, back) = self.region(header, iterations, depth + 1);
+        self.edge(latch, header, back);
+
+        let exit = self.block();
+        self.edge(header, exit, count);
+        (exit, count)
+    }
+
+    /// A condition, a then region, an optional else region, and the block where they join.
+    fn diamond(&mut self, from: usize, count: u64, depth: usize) -> (usize, u64) {
+        let cond = self.block();
+        self.edge(from, cond, count);
+
+        let taken = split(count, self.branch_prob());
+        let (then_last, then_count) = self.region(cond, taken, depth + 1);
+        let (else_last, else_count) = if self.rng.gen_bool(0.5) {
+            self.region(cond, count - taken, depth + 1)
+        } else {
+            (cond, count - taken)
+        };
+
+        let join = self.block();
+        self.edge(then_last, join, then_count);
+        self.edge(else_last, join, else_count);
+        (join, then_count + else_count)
+    }
+
+    /// A switch whose cases are taken with a Zipf-like distribution, joining after them.
+    fn switch(&mut self, from: usize, count: u64, depth: usize) -> (usize, u64) {
+        let head = self.block();
+        self.edge(from, head, count);
+
+        let num_cases = self.rng.gen_range(2..=self.config.max_switch_cases.max(2));
+        let mut shares = (0..num_cases)
+            .map(|k| 1.0 / (k as f64 + 1.0).powf(1.2))
+            .collect::<Vec<f64>>();
+        shares.shuffle(self.rng);
+        let total: f64 = shares.iter().sum();
+
+        let mut cases = vec![];
+        for share in shares {
+            cases.push(self.region(head, split(count, share / total), depth + 1));
+        }
+
+        let join = self.block();
+        let mut out = 0;
+        for (last, case_count) in cases {
+            self.edge(last, join, case_count);
+            out += case_count;
+        }
+        (join, out)
+    }
+
+    /// A check that rarely branches to a cold error block without successors (e.g. a call to
+    /// `abort`), and otherwise continues.
+    fn error_check(&mut self, from: usize, count: u64) -> (usize, u64) {
+        let check = self.block();
+        self.edge(from, check, count);
+
+        let rate = exponential(self.rng, self.config.mean_error_rate).min(1.0);
+        let errors = split(count, rate);
+        let error = self.block();
+        self.edge(check, error, errors);
+
+        let next = self.block();
+        self.edge(check, next, count - errors);
+        (next, count - errors)
+    }
+
+    /// Probability of taking the first side of a two-way branch.
+    fn branch_prob(&mut self) -> f64 {
+        if self.rng.gen_bool(self.config.bias_prob) {
+            let p = self.rng.gen_range(0.9..1.0);
+            if self.rng.gen_bool(0.5) {
+                p
+            } else {
+                1.0 - p
+            }
+        } else {
+            self.rng.gen()
+        }
+    }
+}
+
+/// Part of `count` given by `fraction`, rounded.
+fn split(count: u64, fraction: f64) -> u64 {
+    ((count as f64 * fraction).round() as u64).min(count)
+}
+
+/// Sample of an exponential distribution with the given mean.
+fn exponential(rng: &mut impl Rng, mean: f64) -> f64 {
+    -mean.max(0.0) * (1.0 - rng.gen::<f64>()).ln()
+}
@@ -0,0 +1,129 @@
+use rand::prelude::*;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::neighborhood::{Move, Neighborhood};
+use super::solver::{Run, Solver};
+use super::{CoProblem, Layout, Objective};
+
+/// Tabu search over the swap and insertion neighborhoods. At each iteration the best move of
+/// both neighborhoods is applied, even if it worsens the solution, and the items it moves can't
+/// be moved again for about `tenure` iterations, unless the move improves the best solution found
+/// (aspiration).
+pub struct TabuSearch {
+    /// Mean number of iterations a moved item stays tabu
+    pub tenure: usize,
+}
+
+impl Default for TabuSearch {
+    fn default() -> Self {
+        TabuSearch { tenure: 20 }
+    }
+}
+
+/// The items moved by a swap or an insertion, including the item shifted into the place of the
+/// inserted one, through which adjacent insertions can undo each other.
+fn moved_items(mv: Move, layout: &Layout) -> [usize; 2] {
+    let order = layout.order();
+    match mv {
+        Move::Swap(i, j) | Move::Insert(i, j) => [order[i], order[j]],
+        _ => unreachable!("the tabu search only swaps and inserts"),
+    }
+}
+
+impl Solver for TabuSearch {
+    fn name(&self) -> &'static str {
+        "TS"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let n = problem.n;
+
+        let mut initial = (0..n).collect::<Vec<usize>>();
+        initial.shuffle(&mut run.rng);
+        let initial = problem.constraints.repair(&initial);
+
+        let mut current = Layout::new(problem, objective, initial);
+        let mut best = current.clone();
+        run.evaluated(1, best.fitness());
+
+        // `tabu_until[item]` is the iteration until which `item` can't be moved
+        let mut tabu_until = vec![0; n];
+        let mut iter = 0;
+
+        while n > 1 && !run.is_over() {
+            iter += 1;
+
+            // ties are broken at random, as choosing always the same move of a plateau cycles
+            let mut chosen: Option<(i128, Move)> = None;
+            let mut ties = 0;
+            let mut feasible = false;
+            let moves = Neighborhood::Swap
+                .moves(n)
+                .chain(Neighborhood::Insert.moves(n));
+            for mv in moves {
+                if !mv.is_allowed(problem, &current) {
+                    continue;
+                }
+                feasible = true;
+                let delta = mv.delta(problem, objective, &current);
+                run.evaluated(1, best.fitness());
+
+                let is_tabu = moved_items(mv, &current)
+                    .iter()
+                    .any(|item| tabu_until[*item] > iter);
+                let aspires = current.fitness() as i128 + delta > best.fitness() as i128;
+                if is_tabu && !aspires {
+                    // not admissible
+                } else if Some(delta) > chosen.map(|(d, _)| d) {
+                    chosen = Some((delta, mv));
+                    ties = 1;
+                } else if Some(delta) == chosen.map(|(d, _)| d) {
+                    ties += 1;
+                    if run.rng.gen_range(0..ties) == 0 {
+                        chosen = Some((delta, mv));
+                    }
+                }
+
+                if run.is_over() {
+                    break;
+                }
+            }
+
+            // every move has an inverse, so if the constraints leave no feasible move from the
+            // current solution, none is left from any other solution the search can reach
+            if !feasible && !run.is_over() {
+                break;
+            }
+
+            // if every move is tabu, wait for some of them to be released
+            if let Some((_, mv)) = chosen {
+                // random tenures break the cycles of the search
+                let tenure = run
+                    .rng
+                    .gen_range(self.tenure / 2..=self.tenure + self.tenure / 2);
+                for item in moved_items(mv, &current) {
+                    tabu_until[item] = iter + tenure;
+                }
+
+                mv.apply(problem, objective, &mut current);
+                if current.fitness() > best.fitness() {
+                    best = current.clone();
+                    run.evaluated(0, best.fitness());
+                }
+            }
+        }
+
+        #[cfg(feature = "log")]
+        log::set_attr("tenure", self.tenure);
+
+        let best_f = best.fitness();
+        (best.into_order(), best_f)
+    }
+}
@@ -0,0 +1,183 @@
+use rand::seq::SliceRandom;
+
+#[cfg(feature = "log")]
+use crate::log;
+
+use super::neighborhood::Neighborhood;
+use super::solver::{Run, Solver};
+use super::{CoProblem, Layout, Objective};
+
+/// Variable neighborhood search. Each local optimum found by a variable neighborhood descent
+/// (sweeps of a neighborhood applying every improving move, going back to the first neighborhood
+/// after a sweep that improves) is perturbed with random moves of one of the neighborhoods, which
+/// cycles while the perturbations don't lead to better local optima. The search restarts from a
+/// random solution when the best solution doesn't improve for a while.
+pub struct Vns {
+    /// Neighborhoods of the descent, in the order they are explored. Must not be empty
+    pub neighborhoods: Vec<Neighborhood>,
+    /// Number of random moves of each perturbation
+    pub perturbation: usize,
+    /// Perturbations without improving the best solution after which the search restarts (0 to
+    /// never restart)
+    pub restart_after: usize,
+}
+
+impl Default for Vns {
+    fn default() -> Self {
+        Vns {
+            neighborhoods: vec![
+                Neighborhood::Swap,
+                Neighborhood::Insert,
+                Neighborhood::SegmentMove { max_len: 8 },
+                Neighborhood::Reversal,
+            ],
+            perturbation: 3,
+            restart_after: 100,
+        }
+    }
+}
+
+impl Vns {
+    /// A random solution improved by the descent.
+    fn start(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        best: u128,
+        run: &mut Run,
+    ) -> Layout {
+        let mut initial = (0..problem.n).collect::<Vec<usize>>();
+        initial.shuffle(&mut run.rng);
+        let initial = problem.constraints.repair(&initial);
+
+        let mut layout = Layout::new(problem, objective, initial);
+        run.evaluated(1, best.max(layout.fitness()));
+        self.descend(problem, objective, &mut layout, best, run);
+        layout
+    }
+
+    /// Variable neighborhood descent from `layout` until none of the neighborhoods improves it.
+    fn descend(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        layout: &mut Layout,
+        best: u128,
+        run: &mut Run,
+    ) {
+        let n = layout.order().len();
+        let mut k = 0;
+
+        while k < self.neighborhoods.len() && !run.is_over() {
+            let mut improved = false;
+            for mv in self.neighborhoods[k].moves(n) {
+                if mv.is_allowed(problem, layout) && mv.delta(problem, objective, layout) > 0 {
+                    mv.apply(problem, objective, layout);
+                    improved = true;
+                }
+                run.evaluated(1, best.max(layout.fitness()));
+
+                if run.is_over() {
+                    break;
+                }
+            }
+            // the first neighborhood is swept again, even if it was the one that improved
+            k = if improved { 0 } else { k + 1 };
+        }
+    }
+
+    /// Applies `self.perturbation` random feasible moves of `neighborhood` to `layout`.
+    fn perturb(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        layout: &mut Layout,
+        neighborhood: Neighborhood,
+        best: u128,
+        run: &mut Run,
+    ) {
+        let n = layout.order().len();
+        let mut applied = 0;
+
+        // with many constraints most of the moves may be infeasible, so the tries are limited
+        for _ in 0..(10 * self.perturbation) {
+            if applied == self.perturbation || run.is_over() {
+                break;
+            }
+            let mv = neighborhood.random_move(n, &mut run.rng);
+            if mv.is_allowed(problem, layout) {
+                mv.apply(problem, objective, layout);
+                applied += 1;
+            }
+            run.evaluated(1, best);
+        }
+    }
+}
+
+impl Solver for Vns {
+    fn name(&self) -> &'static str {
+        "VNS"
+    }
+
+    fn search(
+        &self,
+        problem: &CoProblem,
+        objective: &dyn Objective,
+        run: &mut Run,
+    ) -> (Vec<usize>, u128) {
+        let mut incumbent = self.start(problem, objective, 0, run);
+        let mut best = incumbent.clone();
+
+        // neighborhood of the next perturbation
+        let mut shake = 0;
+        // perturbations since the best solution last improved
+        let mut stall = 0;
+
+        while problem.n > 1 && !run.is_over() {
+            let mut candidate = incumbent.clone();
+            let neighborhood = self.neighborhoods[shake];
+            self.perturb(
+                problem,
+                objective,
+                &mut candidate,
+                neighborhood,
+                best.fitness(),
+                run,
+            );
+            self.descend(problem, objective, &mut candidate, best.fitness(), run);
+
+            if candidate.fitness() > incumbent.fitness() {
+                incumbent = candidate;
+                shake = 0;
+            } else {
+                shake = (shake + 1) % self.neighborhoods.len();
+            }
+
+            if incumbent.fitness() > best.fitness() {
+                best = incumbent.clone();
+                stall = 0;
+            } else {
+                stall += 1;
+            }
+
+            if self.restart_after > 0 && stall >= self.restart_after && !run.is_over() {
+                incumbent = self.start(problem, objective, best.fitness(), run);
+                if incumbent.fitness() > best.fitness() {
+                    best = incumbent.clone();
+                }
+                shake = 0;
+                stall = 0;
+            }
+        }
+
+        #[cfg(feature = "log")]
+        {
+            log::set_attr("neighborhoods", format!("{:?}", self.neighborhoods));
+            log::set_attr("perturbation", self.perturbation);
+            log::set_attr("restart after", self.restart_after);
+        }
+
+        let best_f = best.fitness();
+        (best.into_order(), best_f)
+    }
+}
@@ -4,12 +4,17 @@ use llvm_sys as llvm;
 
 use crate::llvm_utils as utils;
 
+/// Reorders the basic blocks of `function` so that the `k`-th block is the `order[k]`-th one of
+/// the original layout. `order[0]` becomes the entry block, so it should be `0`.
 pub fn reorder_blocks(function: LLVMValueRef, order: &[usize]) {
-    let entry = unsafe { LLVMGetFirstBasicBlock(function) };
-    let bbs: Vec<LLVMBasicBlockRef> = utils::get_basic_blocks(function).skip(1).collect();
+    let bbs: Vec<LLVMBasicBlockRef> = utils::get_basic_blocks(function).collect();
     unsafe {
-        for index in order.iter().rev() {
-            LLVMMoveBasicBlockAfter(bbs[*index], entry);
+        let first = LLVMGetFirstBasicBlock(function);
+        if bbs[order[0]] != first {
+            LLVMMoveBasicBlockBefore(bbs[order[0]], first);
+        }
+        for pair in order.windows(2) {
+            LLVMMoveBasicBlockAfter(bbs[pair[1]], bbs[pair[0]]);
         }
     }
 }
@@ -1,89 +1,90 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::prelude::*;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
-struct TabledData {
+/// Data logged by a thread.
+#[derive(Default)]
+pub struct TabledData {
     pub seq_data: HashMap<String, Vec<String>>,
     pub attrs: HashMap<String, String>,
-
-    pub log_path: String,
 }
 
-lazy_static! {
-    static ref DATA: Arc<Mutex<TabledData>> = Arc::new(Mutex::new(TabledData::default()));
+thread_local! {
+    // each thread logs on its own, so that solvers running in parallel don't mix their rows
+    static DATA: RefCell<TabledData> = RefCell::new(TabledData::default());
 }
 
-impl Default for TabledData {
-    fn default() -> Self {
-        Self {
-            seq_data: HashMap::default(),
-            attrs: HashMap::default(),
-            log_path: ".".into(),
-        }
-    }
+lazy_static! {
+    static ref LOG_PATH: Mutex<String> = Mutex::new(".".into());
 }
 
 pub fn log<T: ToString>(key: &str, value: T) {
-    let exists = DATA.lock().unwrap().seq_data.contains_key(key);
-
-    if exists {
-        DATA.lock()
-            .unwrap()
-            .seq_data
-            .get_mut(&key.to_string())
-            .unwrap()
-            .push(value.to_string());
-    } else {
-        DATA.lock()
-            .unwrap()
+    DATA.with(|data| {
+        data.borrow_mut()
             .seq_data
-            .insert(key.to_string(), vec![value.to_string()]);
-    }
+            .entry(key.to_string())
+            .or_default()
+            .push(value.to_string())
+    });
 }
 
 pub fn set_attr<T: ToString>(key: &str, value: T) {
-    DATA.lock()
-        .unwrap()
-        .attrs
-        .insert(key.to_string(), value.to_string());
+    DATA.with(|data| {
+        data.borrow_mut()
+            .attrs
+            .insert(key.to_string(), value.to_string())
+    });
+}
+
+/// Takes the data logged by the current thread, to hand it to the thread that spawned it (see
+/// `merge`), as only the data of the thread that calls `write` is written.
+pub fn take() -> TabledData {
+    DATA.with(|data| std::mem::take(&mut *data.borrow_mut()))
+}
+
+/// Appends the rows logged by another thread to the data of the current one. The attributes of
+/// the other thread are only kept when the current one doesn't have them.
+pub fn merge(other: TabledData) {
+    DATA.with(|data| {
+        let mut data = data.borrow_mut();
+        for (key, values) in other.seq_data {
+            data.seq_data.entry(key).or_default().extend(values);
+        }
+        for (key, value) in other.attrs {
+            data.attrs.entry(key).or_insert(value);
+        }
+    });
 }
 
 pub fn set_log_dir<T: ToString>(dir: T) {
-    DATA.lock().unwrap().log_path = dir.to_string();
+    *LOG_PATH.lock().unwrap() = dir.to_string();
 }
 
+/// Writes the data logged by the current thread to a new file of the log directory, and clears
+/// it.
 pub fn write() {
     let r = rand::random::<usize>();
     set_attr("id", r);
-    let algo = DATA
-        .lock()
-        .unwrap()
-        .attrs
-        .get("algorithm")
-        .unwrap_or(&"unknown".to_string())
-        .clone();
-    let instance = DATA
-        .lock()
-        .unwrap()
-        .attrs
-        .get("instance")
-        .unwrap_or(&"unknown".to_string())
-        .clone();
-    let function = DATA
-        .lock()
-        .unwrap()
-        .attrs
-        .get("function")
-        .unwrap_or(&"function".to_string())
-        .clone();
-    let dir = DATA.lock().unwrap().log_path.clone();
+    let attr = |key: &str, default: &str| {
+        DATA.with(|data| {
+            data.borrow()
+                .attrs
+                .get(key)
+                .map_or(default.to_string(), |v| v.clone())
+        })
+    };
+    let algo = attr("algorithm", "unknown");
+    let instance = attr("instance", "unknown");
+    let function = attr("function", "function");
+    let dir = LOG_PATH.lock().unwrap().clone();
 
     let fname = format!("{dir}/{r}-{algo}-{instance}:{function}.csv");
     let mut file = File::create(&fname).expect(format!("Cannot create log file: {fname}").as_str());
 
-    {
-        let data = &DATA.lock().unwrap();
+    DATA.with(|data| {
+        let data = &data.borrow();
 
         let mut cols = data
             .seq_data
@@ -97,28 +98,25 @@ pub fn write() {
         // println!("{}", cols.join(",").unwrap());
         writeln!(file, "{}", cols.join(",")).unwrap();
 
-        let n = if data.seq_data.is_empty() {
-            1
-        } else {
-            data.seq_data
-                .get(data.seq_data.keys().nth(0).unwrap())
-                .unwrap()
-                .len()
-        };
+        // the columns may have different lengths when rows of other threads were merged
+        let n = data.seq_data.values().map(|v| v.len()).max().unwrap_or(1);
 
         for i in 0..n {
             let values = cols
                 .iter()
                 .map(|c| match data.attrs.get(*c) {
                     Some(v) => v.as_str(),
-                    None => data.seq_data.get(*c).unwrap()[i].as_str(),
+                    None => data.seq_data[*c].get(i).map_or("", |v| v.as_str()),
                 })
                 .collect::<Vec<&str>>();
             //println!("{}", values.join(","));
             writeln!(file, "{}", values.join(",")).unwrap();
         }
-    }
+    });
 
-    DATA.lock().unwrap().seq_data.clear();
-    DATA.lock().unwrap().attrs.clear();
+    DATA.with(|data| {
+        let mut data = data.borrow_mut();
+        data.seq_data.clear();
+        data.attrs.clear();
+    });
 }
@@ -0,0 +1,306 @@
+use llvm::core::*;
+use llvm::object::*;
+use llvm::prelude::*;
+use llvm::target::*;
+use llvm::target_machine::*;
+use llvm::LLVMOpcode;
+use llvm_sys as llvm;
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+
+use crate::llvm_utils as utils;
+
+/// Prefix of the labels that mark the start of each basic block in the emitted code.
+const MARKER_PREFIX: &str = "__pgo_co_bb_";
+
+/// Lowers a copy of the module to machine code and returns the size (in bytes) of each basic
+/// block of each defined function, in the same order as `utils::get_defined_functions`.
+///
+/// If `triple` is `None`, the module's own target triple is used, or the host's if the module
+/// doesn't have any.
+///
+/// Blocks are measured by placing a label at the start of each block (as an inline assembly
+/// call, so codegen keeps them) and reading their addresses from the symbol table of the emitted
+/// object. The code placed before the first label (the prologue) is accounted to the entry block.
+/// Blocks that codegen removes have a size of zero, and the copies of duplicated blocks are
+/// summed up.
+pub fn block_byte_sizes(
+    module: LLVMModuleRef,
+    triple: Option<&str>,
+) -> Result<Vec<Vec<usize>>, String> {
+    let module = unsafe { LLVMCloneModule(module) };
+    let num_bbs = unsafe { insert_block_markers(module) };
+
+    let result = unsafe { emit_object(module, triple) }.and_then(|obj| {
+        let markers = unsafe { read_markers(obj) };
+        unsafe { LLVMDisposeMemoryBuffer(obj) };
+        markers
+    });
+
+    unsafe { LLVMDisposeModule(module) };
+
+    let markers = result?;
+
+    let mut sizes = num_bbs
+        .iter()
+        .map(|n| vec![0; *n])
+        .collect::<Vec<Vec<usize>>>();
+    for (fn_idx, fn_sizes) in sizes.iter_mut().enumerate() {
+        if let Some(fn_markers) = markers.get(&fn_idx) {
+            for (bb_idx, bytes) in fn_markers {
+                fn_sizes[*bb_idx] += *bytes as usize;
+            }
+        }
+    }
+
+    Ok(sizes)
+}
+
+/// Inserts a marker label at the beginning of every basic block. Returns the number of basic
+/// blocks of each defined function.
+// `LLVMConstInlineAsm` and `LLVMBuildCall` are deprecated in newer LLVM versions, but have the
+// same signature in all of them
+#[allow(deprecated)]
+unsafe fn insert_block_markers(module: LLVMModuleRef) -> Vec<usize> {
+    let ctx = LLVMGetModuleContext(module);
+    let builder = LLVMCreateBuilderInContext(ctx);
+    let asm_ty = LLVMFunctionType(LLVMVoidTypeInContext(ctx), std::ptr::null_mut(), 0, 0);
+    let constraints = CString::new("").unwrap();
+
+    let mut num_bbs = vec![];
+    for (fn_idx, fn_ref) in utils::get_defined_functions(module).enumerate() {
+        let bbs = utils::get_basic_blocks(fn_ref).collect::<Vec<LLVMBasicBlockRef>>();
+
+        for (bb_idx, bb) in bbs.iter().enumerate() {
+            let insert_point = match first_insertion_point(*bb) {
+                Some(inst) => inst,
+                None => continue,
+            };
+
+            // `${:uid}` expands to a different number in each copy of the inline assembly, this
+            // keeps labels unique if codegen duplicates the block
+            let label = format!("{MARKER_PREFIX}{fn_idx}_{bb_idx}_${{:uid}}:");
+            let label = CString::new(label).unwrap();
+            let asm = LLVMConstInlineAsm(asm_ty, label.as_ptr(), constraints.as_ptr(), 1, 0);
+
+            LLVMPositionBuilderBefore(builder, insert_point);
+            LLVMBuildCall(
+                builder,
+                asm,
+                std::ptr::null_mut(),
+                0,
+                b"\0".as_ptr() as *const _,
+            );
+        }
+
+        num_bbs.push(bbs.len());
+    }
+
+    LLVMDisposeBuilder(builder);
+
+    num_bbs
+}
+
+/// Returns the instruction a marker has to be inserted before: the first one after the PHI
+/// nodes and exception handling pads. `None` if the block can't hold any other instruction.
+unsafe fn first_insertion_point(bb: LLVMBasicBlockRef) -> Option<LLVMValueRef> {
+    let mut inst = LLVMGetFirstInstruction(bb);
+
+    while !inst.is_null() {
+        match LLVMGetInstructionOpcode(inst) {
+            LLVMOpcode::LLVMPHI
+            | LLVMOpcode::LLVMLandingPad
+            | LLVMOpcode::LLVMCleanupPad
+            | LLVMOpcode::LLVMCatchPad => inst = LLVMGetNextInstruction(inst),
+            LLVMOpcode::LLVMCatchSwitch => return None,
+            _ => return Some(inst),
+        }
+    }
+
+    None
+}
+
+/// Lowers the module into an object file in memory.
+unsafe fn emit_object(
+    module: LLVMModuleRef,
+    triple: Option<&str>,
+) -> Result<LLVMMemoryBufferRef, String> {
+    LLVM_InitializeAllTargetInfos();
+    LLVM_InitializeAllTargets();
+    LLVM_InitializeAllTargetMCs();
+    LLVM_InitializeAllAsmPrinters();
+    // the markers are inline assembly, that has to be parsed
+    LLVM_InitializeAllAsmParsers();
+
+    let module_triple = utils::get_target(module).filter(|t| !t.is_empty());
+
+    // the host CPU is only targeted if we are compiling for the host
+    let (triple, cpu, features) = match (triple, module_triple) {
+        (Some(t), _) => (t.to_string(), "generic".to_string(), String::new()),
+        (None, Some(t)) => (t, "generic".to_string(), String::new()),
+        (None, None) => (
+            take_llvm_string(LLVMGetDefaultTargetTriple()),
+            take_llvm_string(LLVMGetHostCPUName()),
+            take_llvm_string(LLVMGetHostCPUFeatures()),
+        ),
+    };
+    let triple = CString::new(triple).unwrap();
+    let cpu = CString::new(cpu).unwrap();
+    let features = CString::new(features).unwrap();
+
+    let mut target = std::ptr::null_mut();
+    let mut err_str = std::ptr::null_mut();
+    if LLVMGetTargetFromTriple(triple.as_ptr(), &mut target, &mut err_str) != 0 {
+        return Err(take_llvm_string(err_str));
+    }
+
+    let machine = LLVMCreateTargetMachine(
+        target,
+        triple.as_ptr(),
+        cpu.as_ptr(),
+        features.as_ptr(),
+        LLVMCodeGenOptLevel::LLVMCodeGenLevelDefault,
+        LLVMRelocMode::LLVMRelocPIC,
+        LLVMCodeModel::LLVMCodeModelDefault,
+    );
+
+    LLVMSetTarget(module, triple.as_ptr());
+    let data_layout = LLVMCreateTargetDataLayout(machine);
+    LLVMSetModuleDataLayout(module, data_layout);
+    LLVMDisposeTargetData(data_layout);
+
+    let mut obj = std::ptr::null_mut();
+    let ret = LLVMTargetMachineEmitToMemoryBuffer(
+        machine,
+        module,
+        LLVMCodeGenFileType::LLVMObjectFile,
+        &mut err_str,
+        &mut obj,
+    );
+    LLVMDisposeTargetMachine(machine);
+
+    if ret != 0 {
+        return Err(take_llvm_string(err_str));
+    }
+
+    Ok(obj)
+}
+
+/// Reads the markers from the symbol table of the object file. Returns, for each function index,
+/// the list of `(basic block index, bytes)` found.
+unsafe fn read_markers(
+    obj: LLVMMemoryBufferRef,
+) -> Result<HashMap<usize, Vec<(usize, u64)>>, String> {
+    let mut err_str = std::ptr::null_mut();
+    let binary = LLVMCreateBinary(obj, LLVMGetGlobalContext(), &mut err_str);
+    if binary.is_null() {
+        return Err(take_llvm_string(err_str));
+    }
+
+    // all the symbols grouped by section, plus the size of the section
+    let mut sections: HashMap<String, (u64, Vec<(u64, Symbol)>)> = HashMap::new();
+
+    let sym_it = LLVMObjectFileCopySymbolIterator(binary);
+    let sect_it = LLVMObjectFileCopySectionIterator(binary);
+
+    while LLVMObjectFileIsSymbolIteratorAtEnd(binary, sym_it) == 0 {
+        LLVMMoveToContainingSection(sect_it, sym_it);
+
+        if LLVMObjectFileIsSectionIteratorAtEnd(binary, sect_it) == 0 {
+            let name = CStr::from_ptr(LLVMGetSymbolName(sym_it)).to_string_lossy();
+            let sect_name = CStr::from_ptr(LLVMGetSectionName(sect_it))
+                .to_string_lossy()
+                .to_string();
+
+            let (_, symbols) = sections
+                .entry(sect_name)
+                .or_insert_with(|| (LLVMGetSectionSize(sect_it), vec![]));
+
+            let addr = LLVMGetSymbolAddress(sym_it);
+            match parse_marker(&name) {
+                Some((fn_idx, bb_idx)) => symbols.push((addr, Symbol::Marker(fn_idx, bb_idx))),
+                // they mark the switches between code and data inside a function, not its start
+                None if is_mapping_symbol(&name) => (),
+                None => {
+                    symbols.push((addr, Symbol::Start));
+                    // not all object formats record the size of the symbols
+                    let size = LLVMGetSymbolSize(sym_it);
+                    if size > 0 {
+                        symbols.push((addr + size, Symbol::End));
+                    }
+                }
+            }
+        }
+
+        LLVMMoveToNextSymbol(sym_it);
+    }
+
+    LLVMDisposeSectionIterator(sect_it);
+    LLVMDisposeSymbolIterator(sym_it);
+    LLVMDisposeBinary(binary);
+
+    let mut markers: HashMap<usize, Vec<(usize, u64)>> = HashMap::new();
+
+    for (_, (sect_size, mut symbols)) in sections {
+        symbols.sort();
+
+        // start of the code before the first marker of a function, it goes to the entry block
+        let mut pending_start = None;
+
+        for (idx, (addr, symbol)) in symbols.iter().enumerate() {
+            let next_addr = symbols.get(idx + 1).map(|(a, _)| *a).unwrap_or(sect_size);
+
+            match symbol {
+                Symbol::Marker(fn_idx, bb_idx) => {
+                    let list = markers.entry(*fn_idx).or_default();
+                    if let Some(start) = pending_start.take() {
+                        list.push((0, *addr - start));
+                    }
+                    list.push((*bb_idx, next_addr - *addr));
+                }
+                Symbol::Start => pending_start = Some(*addr),
+                Symbol::End => pending_start = None,
+            }
+        }
+    }
+
+    Ok(markers)
+}
+
+/// Kinds of symbol found in the object file. The order is the one in which symbols at the same
+/// address are processed: the end of a function comes before the start of the next one, and
+/// that one before its first marker.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Symbol {
+    End,
+    Start,
+    Marker(usize, usize),
+}
+
+/// Parses a marker label into `(function index, basic block index)`.
+fn parse_marker(name: &str) -> Option<(usize, usize)> {
+    let mut fields = name.strip_prefix(MARKER_PREFIX)?.split('_');
+    let fn_idx = fields.next()?.parse().ok()?;
+    let bb_idx = fields.next()?.parse().ok()?;
+    Some((fn_idx, bb_idx))
+}
+
+/// Returns `true` for the mapping symbols of the ARM and AArch64 ELF objects (`$a`, `$d`, `$t`
+/// and `$x`, optionally followed by `.` and a suffix).
+fn is_mapping_symbol(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some('$')
+        && matches!(chars.next(), Some('a' | 'd' | 't' | 'x'))
+        && matches!(chars.next(), None | Some('.'))
+}
+
+/// Copies a string allocated by LLVM and frees it.
+unsafe fn take_llvm_string(ptr: *mut ::libc::c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    let s = CStr::from_ptr(ptr).to_string_lossy().to_string();
+    LLVMDisposeMessage(ptr);
+    s
+}
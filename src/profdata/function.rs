@@ -4,7 +4,7 @@ use llvm::core::*;
 use llvm::prelude::*;
 use llvm::LLVMOpcode;
 
-use super::Metadata;
+use super::{inference, InferredWeights, Metadata};
 use crate::llvm_utils as utils;
 
 #[derive(Debug)]
@@ -16,6 +16,23 @@ pub struct Function {
     pub function_ref: LLVMValueRef,
     pub bbs_branch_tree: Vec<Vec<usize>>,
     pub bbs_num_instrs: Vec<usize>,
+    /// Size in bytes of the machine code of each basic block, only available after calling
+    /// `Module::measure_block_bytes`
+    pub bbs_num_bytes: Option<Vec<usize>>,
+    /// Direct calls to other functions made from the function
+    pub call_sites: Vec<CallSite>,
+}
+
+/// A `call` or `invoke` instruction whose callee is known.
+#[derive(Debug)]
+pub struct CallSite {
+    /// Index of the basic block that contains the call
+    pub bb: usize,
+    /// Name of the called function
+    pub callee: String,
+    /// Number of times the call was executed, if the profile records it (as a single
+    /// `branch_weights` operand on the call, as sample profiles do)
+    pub count: Option<u64>,
 }
 
 impl Function {
@@ -31,7 +48,14 @@ impl Function {
         let mut bbs_num_instrs = vec![];
 
         let mut bb_terminator_md = vec![];
+        let mut call_sites = vec![];
         for (i, bb_ref) in bbs_refs.iter().enumerate() {
+            for instr in utils::get_instructions(*bb_ref) {
+                if let Some(call_site) = CallSite::from_instruction(instr, i, kind_id) {
+                    call_sites.push(call_site);
+                }
+            }
+
             // count the number of instructions in the block
             let mut num_instr = 1;
             unsafe {
@@ -50,22 +74,17 @@ impl Function {
             let term = unsafe { LLVMGetBasicBlockTerminator(*bb_ref) };
 
             // extract terminator instruction's metadata
-            if unsafe { LLVMGetInstructionOpcode(term) } == LLVMOpcode::LLVMBr {
-                bb_terminator_md.push(Metadata::extract_metadata(term, kind_id));
-
-                // get the parent of the current basick block
-                let num_operands = unsafe { LLVMGetNumOperands(term) } as u32;
-                // the first operand of the `br` instruction is skipped when it refers to the
-                // condition value of the branch operation
-                let start_idx = if num_operands > 1 { 1 } else { 0 };
-                // NOTE: for some reason `rev` has to be used to read the operands
-                // in the same order as in the LLVM-IR
-                for op_idx in (start_idx..num_operands).rev() {
-                    let bb_to_jump = unsafe { LLVMValueAsBasicBlock(LLVMGetOperand(term, op_idx)) };
-                    branch_tree[i].push(bbs_refs.iter().position(|&bb| bb == bb_to_jump).unwrap());
-                }
-            } else {
-                bb_terminator_md.push(None);
+            bb_terminator_md.push(Metadata::extract_metadata(term, kind_id));
+
+            // successors are read in the same order as the operands of the `branch_weights`
+            // metadata: `br` (true, false), `switch` (default, cases...), `invoke` (normal,
+            // unwind), `callbr` (default, indirect...) and `indirectbr` (destinations...).
+            // NOTE: a block may appear more than once, e.g. several `switch` cases jumping to
+            // the same block
+            let num_succs = unsafe { LLVMGetNumSuccessors(term) };
+            for succ_idx in 0..num_succs {
+                let bb_to_jump = unsafe { LLVMGetSuccessor(term, succ_idx) };
+                branch_tree[i].push(bbs_refs.iter().position(|&bb| bb == bb_to_jump).unwrap());
             }
         }
 
@@ -77,6 +96,8 @@ impl Function {
             function_ref: fn_ref,
             bbs_branch_tree: branch_tree,
             bbs_num_instrs,
+            bbs_num_bytes: None,
+            call_sites,
         }
     }
 
@@ -102,6 +123,45 @@ impl Function {
         None
     }
 
+    /// Returns the `branch_weights` of the terminator of the `bb`-th basic block, one for each
+    /// entry of `bbs_branch_tree[bb]`. Missing trailing weights (e.g. an `invoke` with only the
+    /// weight of its normal destination) are set to zero.
+    pub fn get_branch_weights(&self, bb: usize) -> Option<Vec<u64>> {
+        if let Some(Some(Metadata::Node(leafs))) = self.bb_terminator_md.get(bb) {
+            let mut leafs_iter = leafs.iter();
+
+            // the terminator can have other kinds of `prof` metadata, such as the value profile
+            // (`VP`) of an indirect `invoke`
+            match leafs_iter.next() {
+                Some(Metadata::String(name)) if name == "branch_weights" => (),
+                _ => return None,
+            }
+
+            let mut weights: Vec<u64> = leafs_iter
+                .map(|v| match v {
+                    Metadata::IntValue(w) => *w,
+                    _ => unreachable!(),
+                })
+                .collect();
+            weights.resize(self.bbs_branch_tree[bb].len(), 0);
+
+            return Some(weights);
+        }
+        None
+    }
+
+    /// Returns the weight of every branch of the CFG, inferring the missing ones so that the
+    /// flow is conserved in each basic block (see `inference::infer_branch_weights`).
+    pub fn infer_branch_weights(&self) -> InferredWeights {
+        inference::infer_branch_weights(self)
+    }
+
+    /// Returns the size of each basic block: its machine code size in bytes if it has been
+    /// measured, the number of instructions otherwise.
+    pub fn bbs_sizes(&self) -> &[usize] {
+        self.bbs_num_bytes.as_ref().unwrap_or(&self.bbs_num_instrs)
+    }
+
     pub fn get_id(&self) -> String {
         unsafe {
             let mut len = 0;
@@ -183,3 +243,40 @@ impl Function {
     }
     */
 }
+
+impl CallSite {
+    /// Returns the call site of `instr` if it's a call to a function other than an intrinsic.
+    fn from_instruction(instr: LLVMValueRef, bb: usize, kind_id: u32) -> Option<CallSite> {
+        let opcode = unsafe { LLVMGetInstructionOpcode(instr) };
+        if opcode != LLVMOpcode::LLVMCall && opcode != LLVMOpcode::LLVMInvoke {
+            return None;
+        }
+
+        let callee = unsafe { LLVMGetCalledValue(instr) };
+        // indirect calls are skipped, their value profile (`VP`) refers to the callees by the
+        // MD5 hash of their name
+        if unsafe { LLVMIsAFunction(callee) }.is_null()
+            || unsafe { LLVMGetIntrinsicID(callee) } != 0
+        {
+            return None;
+        }
+
+        // `invoke` instructions are terminators, their `branch_weights` are the ones of the
+        // normal and unwind destinations
+        let count = match Metadata::extract_metadata(instr, kind_id) {
+            Some(Metadata::Node(leafs)) if opcode == LLVMOpcode::LLVMCall => match &leafs[..] {
+                [Metadata::String(name), Metadata::IntValue(count)] if name == "branch_weights" => {
+                    Some(*count)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        Some(CallSite {
+            bb,
+            callee: unsafe { utils::get_value_name(callee) },
+            count,
+        })
+    }
+}
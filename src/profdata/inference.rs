@@ -0,0 +1,235 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::Function;
+
+/// Branch weights of a function made consistent with flow conservation (see
+/// `Function::infer_branch_weights`).
+#[derive(Debug, Clone, Default)]
+pub struct InferredWeights {
+    /// Weight of each `(from, to)` branch of the CFG
+    pub weights: HashMap<(usize, usize), u64>,
+    /// Number of times each basic block was executed, consistent with `weights`
+    pub block_counts: Vec<u64>,
+    /// Number of branches that had no weight in the profile
+    pub num_inferred: usize,
+    /// Total amount by which the weights in the profile (and the entry count) had to be changed
+    /// to make them consistent. Zero if the profile already satisfied flow conservation.
+    pub violation: u64,
+}
+
+/// "Infinite" capacity, small enough to not overflow when flows are added to it
+const INF: u64 = u64::MAX / 4;
+
+/// Infers the weight of every branch of the CFG from the entry count and the known branch
+/// weights, in the spirit of LLVM's profile inference (profi).
+///
+/// The problem is posed as a minimum cost circulation over the CFG, where a virtual node feeds
+/// the entry block and collects the flow of the exit blocks. Every branch starts with its known
+/// weight (or zero) and can be increased or decreased at a cost: changing a known weight is more
+/// expensive than routing the flow through any path of branches without weight, so known
+/// weights are only modified when they violate flow conservation.
+pub fn infer_branch_weights(function: &Function) -> InferredWeights {
+    let num_bbs = function.num_bbs;
+
+    // distinct branches, the weights of repeated successors are summed up
+    let mut known: HashMap<(usize, usize), Option<u64>> = HashMap::new();
+    for (from, succs) in function.bbs_branch_tree.iter().enumerate() {
+        let weights = function.get_branch_weights(from);
+        for (j, to) in succs.iter().enumerate() {
+            let w = known.entry((from, *to)).or_insert(Some(0));
+            *w = match (&weights, *w) {
+                (Some(weights), Some(acc)) => Some(acc + weights[j]),
+                _ => None,
+            };
+        }
+    }
+
+    let num_inferred = known.values().filter(|w| w.is_none()).count();
+
+    // the arcs of the flow network, in the same order as `known` plus the arcs from and to the
+    // virtual node. Self loops don't take part in flow conservation.
+    let virtual_node = num_bbs;
+    let mut arcs: Vec<(usize, usize, Option<u64>)> = known
+        .iter()
+        .filter(|((from, to), _)| from != to)
+        .map(|((from, to), w)| (*from, *to, *w))
+        .collect();
+    if num_bbs > 0 {
+        arcs.push((virtual_node, 0, function.get_entry_count()));
+    }
+    for (bb, succs) in function.bbs_branch_tree.iter().enumerate() {
+        if succs.is_empty() {
+            arcs.push((bb, virtual_node, None));
+        }
+    }
+
+    let flows = min_cost_circulation(num_bbs + 1, &arcs);
+
+    let mut violation = 0;
+    let mut weights = HashMap::new();
+    let mut block_counts = vec![0; num_bbs];
+    for ((from, to, w), flow) in arcs.iter().zip(flows) {
+        if let Some(w) = w {
+            violation += if flow > *w { flow - w } else { w - flow };
+        }
+        if *to != virtual_node {
+            block_counts[*to] += flow;
+            if *from != virtual_node {
+                weights.insert((*from, *to), flow);
+            }
+        }
+    }
+
+    // self loops keep their known weight, as nothing constrains them
+    for ((from, to), w) in known.iter() {
+        if from == to {
+            weights.insert((*from, *to), w.unwrap_or(0));
+            block_counts[*to] += w.unwrap_or(0);
+        }
+    }
+
+    InferredWeights {
+        weights,
+        block_counts,
+        num_inferred,
+        violation,
+    }
+}
+
+/// Given a list of `(from, to, weight)` arcs, where unknown weights are `None`, returns the flow
+/// of each arc in a circulation that minimizes the changes to the known weights.
+fn min_cost_circulation(num_nodes: usize, arcs: &[(usize, usize, Option<u64>)]) -> Vec<u64> {
+    // cost of changing a known weight by one unit, higher than any path of unknown arcs
+    let known_cost = 2 * arcs.len() as i64 + 2;
+
+    let source = num_nodes;
+    let sink = num_nodes + 1;
+    let mut net = Network::new(num_nodes + 2);
+
+    // imbalance of each node with the initial flow (the known weights)
+    let mut excess = vec![0i128; num_nodes];
+    // id of the arcs that increase and decrease the flow of each arc
+    let mut ids = Vec::with_capacity(arcs.len());
+
+    for (from, to, w) in arcs.iter() {
+        let (w, cost) = match w {
+            Some(w) => (*w, known_cost),
+            None => (0, 1),
+        };
+        excess[*from] -= w as i128;
+        excess[*to] += w as i128;
+
+        let inc = net.add_arc(*from, *to, INF, cost);
+        let dec = net.add_arc(*to, *from, w, known_cost);
+        ids.push((w, inc, dec));
+    }
+
+    for (node, e) in excess.iter().enumerate() {
+        let cap = e.unsigned_abs().min(INF as u128) as u64;
+        if *e > 0 {
+            net.add_arc(source, node, cap, 0);
+        } else if *e < 0 {
+            net.add_arc(node, sink, cap, 0);
+        }
+    }
+
+    net.min_cost_flow(source, sink);
+
+    ids.iter()
+        .map(|(w, inc, dec)| w + net.flow(*inc) - net.flow(*dec))
+        .collect()
+}
+
+/// Residual network for the successive shortest paths algorithm. Arc `2k` is the `k`-th arc
+/// added and `2k + 1` its reverse.
+struct Network {
+    /// Outgoing arcs of each node
+    adj: Vec<Vec<usize>>,
+    /// `(to, residual capacity, cost)` of each arc
+    arcs: Vec<(usize, u64, i64)>,
+}
+
+impl Network {
+    fn new(num_nodes: usize) -> Self {
+        Network {
+            adj: vec![vec![]; num_nodes],
+            arcs: vec![],
+        }
+    }
+
+    fn add_arc(&mut self, from: usize, to: usize, cap: u64, cost: i64) -> usize {
+        let id = self.arcs.len();
+        self.adj[from].push(id);
+        self.arcs.push((to, cap, cost));
+        self.adj[to].push(id + 1);
+        self.arcs.push((from, 0, -cost));
+        id
+    }
+
+    /// Flow sent through the arc with the given id.
+    fn flow(&self, id: usize) -> u64 {
+        self.arcs[id + 1].1
+    }
+
+    /// Sends as much flow as possible from `source` to `sink` at minimum cost. All the costs
+    /// must be nonnegative.
+    fn min_cost_flow(&mut self, source: usize, sink: usize) {
+        let n = self.adj.len();
+        let mut potential = vec![0i64; n];
+
+        loop {
+            // Dijkstra over the reduced costs
+            let mut dist = vec![i64::MAX; n];
+            let mut pred = vec![usize::MAX; n];
+            let mut heap = BinaryHeap::new();
+            dist[source] = 0;
+            heap.push(Reverse((0, source)));
+
+            while let Some(Reverse((d, u))) = heap.pop() {
+                if d > dist[u] {
+                    continue;
+                }
+                for &id in self.adj[u].iter() {
+                    let (v, cap, cost) = self.arcs[id];
+                    if cap == 0 {
+                        continue;
+                    }
+                    let nd = d + cost + potential[u] - potential[v];
+                    if nd < dist[v] {
+                        dist[v] = nd;
+                        pred[v] = id;
+                        heap.push(Reverse((nd, v)));
+                    }
+                }
+            }
+
+            if dist[sink] == i64::MAX {
+                break;
+            }
+
+            for (p, d) in potential.iter_mut().zip(dist.iter()) {
+                if *d != i64::MAX {
+                    *p += d;
+                }
+            }
+
+            // bottleneck of the path
+            let mut bottleneck = INF;
+            let mut v = sink;
+            while v != source {
+                let id = pred[v];
+                bottleneck = bottleneck.min(self.arcs[id].1);
+                v = self.arcs[id ^ 1].0;
+            }
+
+            let mut v = sink;
+            while v != source {
+                let id = pred[v];
+                self.arcs[id].1 -= bottleneck;
+                self.arcs[id ^ 1].1 += bottleneck;
+                v = self.arcs[id ^ 1].0;
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
+mod codesize;
 mod context;
 mod function;
+mod inference;
 mod metadata;
 mod module;
 
 pub use context::Context;
-pub use function::Function;
+pub use function::{CallSite, Function};
+pub use inference::InferredWeights;
 pub use metadata::Metadata;
 pub use module::Module;
@@ -8,7 +8,7 @@ use std::mem;
 use std::mem::MaybeUninit;
 use std::path::Path;
 
-use super::{Context, Function};
+use super::{codesize, Context, Function};
 use crate::llvm_utils as utils;
 
 #[derive(Debug)]
@@ -138,6 +138,31 @@ impl Module {
         funcs.iter().rev().map(|&(v, f)| (v, f)).collect()
     }
 
+    /// Measures the machine code size of each basic block of the module, lowering it for the
+    /// given target triple (see `codesize::block_byte_sizes`). The sizes are stored in the
+    /// `bbs_num_bytes` field of each function.
+    pub fn measure_block_bytes(&mut self, triple: Option<&str>) -> Result<(), String> {
+        let sizes = codesize::block_byte_sizes(self.module_ref, triple)?;
+
+        for (func, bytes) in self.functions.iter_mut().zip(sizes) {
+            func.bbs_num_bytes = Some(bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a named metadata node holding a single string, `!name = !{!"value"}`.
+    pub fn add_named_metadata(&self, name: &str, value: &str) {
+        unsafe {
+            let ctx = LLVMGetModuleContext(self.module_ref);
+            let name_cstr = utils::to_c_str(name);
+            let mut string =
+                LLVMMDStringInContext2(ctx, value.as_ptr() as *const ::libc::c_char, value.len());
+            let node = LLVMMetadataAsValue(ctx, LLVMMDNodeInContext2(ctx, &mut string, 1));
+            LLVMAddNamedMetadataOperand(self.module_ref, name_cstr.as_ptr(), node);
+        }
+    }
+
     pub fn to_path(&self, path_str: &str) -> Result<(), String> {
         unsafe {
             let filename_cstr = utils::to_c_str(path_str);